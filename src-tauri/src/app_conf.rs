@@ -1,5 +1,8 @@
+use crate::db::policy::TtlOverride;
+use crate::image_cache::ImageVariant;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
@@ -12,12 +15,71 @@ pub struct AppConf {
     pub oauth_port: u16,
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    // 本地发行日期、分级使用的国家代码 (ISO 3166-1 alpha-2，小写)
+    #[serde(default = "default_home_country")]
+    pub home_country: String,
+    // 缓存上限：每张缓存表的最大行数和数据库文件的最大体积 (MB)
+    #[serde(default = "default_cache_max_rows")]
+    pub cache_max_rows: u32,
+    #[serde(default = "default_cache_max_db_mb")]
+    pub cache_max_db_mb: u32,
+    // 本地图片缓存目录的最大体积 (MB)
+    #[serde(default = "default_image_cache_max_mb")]
+    pub image_cache_max_mb: u32,
+    // 列表返回后在后台预取海报，默认关闭；前 image_prefetch_visible 项优先
+    #[serde(default)]
+    pub image_prefetch_enabled: bool,
+    #[serde(default = "default_image_prefetch_variant")]
+    pub image_prefetch_variant: ImageVariant,
+    #[serde(default = "default_image_prefetch_visible")]
+    pub image_prefetch_visible: u32,
+    // 翻译首选语言，可以带地区，如 zh-CN / zh-TW
+    #[serde(default = "default_translation_language")]
+    pub translation_language: String,
+    // 首选语言没有翻译时依次尝试，original 表示使用原文
+    #[serde(default = "default_translation_fallbacks")]
+    pub translation_fallbacks: Vec<String>,
+    // 按缓存策略名覆盖 TTL 和陈旧时间
+    #[serde(default)]
+    pub cache_ttl_overrides: HashMap<String, TtlOverride>,
 }
 
 fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_home_country() -> String {
+    "us".to_string()
+}
+
+pub(crate) fn default_translation_language() -> String {
+    "zh-CN".to_string()
+}
+
+pub(crate) fn default_translation_fallbacks() -> Vec<String> {
+    ["zh-TW", "zh-HK", "zh", "original"].iter().map(|s| s.to_string()).collect()
+}
+
+fn default_cache_max_rows() -> u32 {
+    5000
+}
+
+fn default_cache_max_db_mb() -> u32 {
+    256
+}
+
+fn default_image_cache_max_mb() -> u32 {
+    200
+}
+
+fn default_image_prefetch_variant() -> ImageVariant {
+    ImageVariant::PosterSmall
+}
+
+fn default_image_prefetch_visible() -> u32 {
+    12
+}
+
 impl AppConf {
     fn new() -> Self {
         let client_id = option_env!("TRAKT_CLIENT_ID");
@@ -33,6 +95,16 @@ impl AppConf {
                     redirect_uri: uri.to_string(),
                     oauth_port: oauth_port.and_then(|p| p.parse().ok()).unwrap_or(4396),
                     log_level: default_log_level(),
+                    home_country: default_home_country(),
+                    cache_max_rows: default_cache_max_rows(),
+                    cache_max_db_mb: default_cache_max_db_mb(),
+                    image_cache_max_mb: default_image_cache_max_mb(),
+                    image_prefetch_enabled: false,
+                    image_prefetch_variant: default_image_prefetch_variant(),
+                    image_prefetch_visible: default_image_prefetch_visible(),
+                    translation_language: default_translation_language(),
+                    translation_fallbacks: default_translation_fallbacks(),
+                    cache_ttl_overrides: HashMap::new(),
                 };
             }
         }
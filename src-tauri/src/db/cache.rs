@@ -1,6 +1,8 @@
 use sqlx::{SqlitePool, Row};
 use serde_json::Value;
 use log::{info, debug, error};
+use std::sync::atomic::{AtomicU64, Ordering};
+use super::policy::CacheStore;
 
 // 获取当前时间戳 (ms)
 pub fn get_timestamp() -> i64 {
@@ -17,6 +19,139 @@ pub const CACHE_TTL_TRANSLATION: i64 = 7 * 24 * 60 * 60 * 1000; // 7天
 pub const STALE_WHILE_REVALIDATE: i64 = 60 * 60 * 1000;   // 1小时后视为陈旧，需要后台更新
 pub const STALE_WHILE_REVALIDATE_USER: i64 = 5 * 60 * 1000; // 用户数据 5分钟后视为陈旧
 pub const CACHE_TTL_API: i64 = 4 * 60 * 60 * 1000;        // 列表API缓存4小时
+pub const CACHE_TTL_INSIGHTS: i64 = 10 * 60 * 1000;       // 统计/正在观看 10分钟
+pub const STALE_WHILE_REVALIDATE_TRACKED: i64 = 7 * 24 * 60 * 60 * 1000; // 由 updates 任务跟踪变化的条目 7天后才视为陈旧
+
+// 这些类型的变化由 /movies/updates 和 /shows/updates 跟踪，有变化时会被主动标记为陈旧
+pub const UPDATE_TRACKED_MOVIE_TYPES: [&str; 1] = ["movie"];
+pub const UPDATE_TRACKED_SHOW_TYPES: [&str; 3] = ["show", "show_seasons", "show_season_tree"];
+
+fn stale_window(media_type: &str) -> i64 {
+    if UPDATE_TRACKED_MOVIE_TYPES.contains(&media_type) || UPDATE_TRACKED_SHOW_TYPES.contains(&media_type) {
+        STALE_WHILE_REVALIDATE_TRACKED
+    } else {
+        STALE_WHILE_REVALIDATE
+    }
+}
+
+// 记录最近一次读取时间，供缓存维护按 LRU 淘汰
+async fn touch(pool: &SqlitePool, table: &str, key_column: &str, key: &str, now: i64) {
+    let sql = format!("UPDATE {} SET last_accessed_at = ? WHERE {} = ?", table, key_column);
+    if let Err(e) = sqlx::query(&sql).bind(now).bind(key).execute(pool).await {
+        debug!("Failed to touch {} {}: {}", table, key, e);
+    }
+}
+
+/// 进程内的命中/未命中计数，供缓存检查工具计算命中率，重启后清零
+pub struct HitCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HitCounter {
+    const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+}
+
+pub static MEDIA_COUNTER: HitCounter = HitCounter::new();
+pub static API_COUNTER: HitCounter = HitCounter::new();
+pub static USER_DATA_COUNTER: HitCounter = HitCounter::new();
+pub static TRANSLATION_COUNTER: HitCounter = HitCounter::new();
+
+pub fn counter_for(store: CacheStore) -> &'static HitCounter {
+    match store {
+        CacheStore::Media => &MEDIA_COUNTER,
+        CacheStore::ApiResponse => &API_COUNTER,
+        CacheStore::UserData => &USER_DATA_COUNTER,
+    }
+}
+
+/// 通用缓存 key：media_cache 使用 (类型, trakt id)，其余表使用字符串 key
+#[derive(Debug, Clone)]
+pub enum CacheKey {
+    Media { media_type: String, trakt_id: u32 },
+    Key(String),
+}
+
+impl CacheKey {
+    pub fn media(media_type: &str, trakt_id: u32) -> Self {
+        CacheKey::Media { media_type: media_type.to_string(), trakt_id }
+    }
+
+    pub fn row_id(&self) -> String {
+        match self {
+            CacheKey::Media { media_type, trakt_id } => format!("{}_{}", media_type, trakt_id),
+            CacheKey::Key(key) => key.clone(),
+        }
+    }
+}
+
+/// 未经判断的原始缓存行，新鲜程度由调用方的缓存策略决定
+pub struct CacheEntry {
+    pub data: Value,
+    pub updated_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+pub async fn read_cache_entry(pool: &SqlitePool, store: CacheStore, key: &CacheKey) -> Option<CacheEntry> {
+    let (table, key_column, expires) = match store {
+        CacheStore::Media => ("media_cache", "id", "expires_at"),
+        CacheStore::ApiResponse => ("api_response_cache", "key", "expires_at"),
+        CacheStore::UserData => ("user_data_cache", "key", "NULL"),
+    };
+    let row_id = key.row_id();
+    let sql = format!(
+        "SELECT data, updated_at, {} AS expires_at FROM {} WHERE {} = ?",
+        expires, table, key_column
+    );
+
+    match sqlx::query(&sql).bind(&row_id).fetch_optional(pool).await {
+        Ok(Some(row)) => {
+            let data_str: String = row.get("data");
+            let data = serde_json::from_str(&data_str).ok()?;
+            touch(pool, table, key_column, &row_id, get_timestamp()).await;
+            Some(CacheEntry {
+                data,
+                updated_at: row.get("updated_at"),
+                expires_at: row.get("expires_at"),
+            })
+        }
+        Ok(None) => None,
+        Err(e) => {
+            error!("DB error reading {} {}: {}", table, row_id, e);
+            None
+        }
+    }
+}
+
+pub async fn write_cache_entry(pool: &SqlitePool, store: CacheStore, key: &CacheKey, data: &Value, ttl_ms: i64) {
+    match (store, key) {
+        (CacheStore::Media, CacheKey::Media { media_type, trakt_id }) => {
+            set_media_cache(pool, media_type, *trakt_id, data, ttl_ms).await
+        }
+        (CacheStore::ApiResponse, CacheKey::Key(key)) => {
+            set_api_response_cache_with_ttl(pool, key, data, ttl_ms).await
+        }
+        (CacheStore::UserData, CacheKey::Key(key)) => set_user_data_cache(pool, key, data).await,
+        _ => error!("Cache key {} does not match store {:?}", key.row_id(), store),
+    }
+}
 
 pub struct CacheResult {
     pub data: Value,
@@ -41,6 +176,7 @@ pub async fn get_media_cache(pool: &SqlitePool, media_type: &str, trakt_id: u32)
             if now > expires_at {
                 info!("🔴 Cache EXPIRED for {}", id);
                 let _ = sqlx::query("DELETE FROM media_cache WHERE id = ?").bind(&id).execute(pool).await;
+                MEDIA_COUNTER.miss();
                 return None;
             }
             
@@ -48,9 +184,11 @@ pub async fn get_media_cache(pool: &SqlitePool, media_type: &str, trakt_id: u32)
             if let Ok(json) = serde_json::from_str(&data_str) {
                 // 检查是否陈旧
                 let updated_at: i64 = row.get("updated_at");
-                let is_stale = (updated_at + STALE_WHILE_REVALIDATE) < now;
+                let is_stale = (updated_at + stale_window(media_type)) < now;
                 
+                touch(pool, "media_cache", "id", &id, now).await;
                 info!("🟢 Cache HIT for {} (Stale: {})", id, is_stale);
+                MEDIA_COUNTER.hit();
                 return Some(CacheResult {
                     data: json,
                     is_stale
@@ -61,6 +199,7 @@ pub async fn get_media_cache(pool: &SqlitePool, media_type: &str, trakt_id: u32)
         Err(e) => error!("DB error getting cache for {}: {}", id, e),
     }
     
+    MEDIA_COUNTER.miss();
     None
 }
 
@@ -71,7 +210,7 @@ pub async fn set_media_cache(pool: &SqlitePool, media_type: &str, trakt_id: u32,
     let data_str = data.to_string();
 
     let result = sqlx::query(
-        "INSERT OR REPLACE INTO media_cache (id, media_type, trakt_id, data, updated_at, expires_at) VALUES (?, ?, ?, ?, ?, ?)"
+        "INSERT OR REPLACE INTO media_cache (id, media_type, trakt_id, data, updated_at, expires_at, last_accessed_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&id)
     .bind(media_type)
@@ -79,6 +218,7 @@ pub async fn set_media_cache(pool: &SqlitePool, media_type: &str, trakt_id: u32,
     .bind(data_str)
     .bind(now)
     .bind(expires_at)
+    .bind(now)
     .execute(pool)
     .await;
 
@@ -89,6 +229,43 @@ pub async fn set_media_cache(pool: &SqlitePool, media_type: &str, trakt_id: u32,
     }
 }
 
+// 将指定条目标记为陈旧（updated_at 置 0），下次读取时会在后台刷新，返回受影响的行数
+pub async fn mark_media_cache_stale(pool: &SqlitePool, media_type: &str, trakt_ids: &[u32]) -> u64 {
+    let mut affected = 0;
+    // SQLite 单条语句的参数数量有限，分批执行
+    for chunk in trakt_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            "UPDATE media_cache SET updated_at = 0 WHERE media_type = ? AND trakt_id IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query(&sql).bind(media_type);
+        for id in chunk {
+            query = query.bind(*id);
+        }
+        match query.execute(pool).await {
+            Ok(result) => affected += result.rows_affected(),
+            Err(e) => error!("Failed to mark {} cache stale: {}", media_type, e),
+        }
+    }
+    affected
+}
+
+// 无法得知哪些条目有变化时，整体标记为陈旧
+pub async fn mark_media_type_stale(pool: &SqlitePool, media_type: &str) -> u64 {
+    match sqlx::query("UPDATE media_cache SET updated_at = 0 WHERE media_type = ?")
+        .bind(media_type)
+        .execute(pool)
+        .await
+    {
+        Ok(result) => result.rows_affected(),
+        Err(e) => {
+            error!("Failed to mark {} cache stale: {}", media_type, e);
+            0
+        }
+    }
+}
+
 // 翻译缓存操作
 pub async fn get_translation_cache(pool: &SqlitePool, media_type: &str, id: u32) -> Option<Value> {
     let cache_id = format!("{}_{}", media_type, id);
@@ -104,6 +281,7 @@ pub async fn get_translation_cache(pool: &SqlitePool, media_type: &str, id: u32)
             let expires_at: i64 = row.get("expires_at");
             if now > expires_at {
                 let _ = sqlx::query("DELETE FROM translation_cache WHERE id = ?").bind(&cache_id).execute(pool).await;
+                TRANSLATION_COUNTER.miss();
                 return None;
             }
 
@@ -118,11 +296,13 @@ pub async fn get_translation_cache(pool: &SqlitePool, media_type: &str, id: u32)
                 "updated_at": now
             });
             info!("🟢 Translation Cache HIT for {}", cache_id);
+            TRANSLATION_COUNTER.hit();
             return Some(json);
         }
         _ => {
             // Translation misses happen often and are noisy, keeping it debug
             debug!("⚪️ Translation Cache MISS for {}", cache_id);
+            TRANSLATION_COUNTER.miss();
             return None;
         }
     }
@@ -209,7 +389,9 @@ pub async fn get_user_data_cache(pool: &SqlitePool, key: &str) -> Option<CacheRe
                 let updated_at: i64 = row.get("updated_at");
                 let is_stale = (updated_at + STALE_WHILE_REVALIDATE_USER) < now;
                 
+                touch(pool, "user_data_cache", "key", key, now).await;
                 info!("🟢 User Data Cache HIT for {} (Stale: {})", key, is_stale);
+                USER_DATA_COUNTER.hit();
                 return Some(CacheResult {
                     data: json,
                     is_stale
@@ -220,6 +402,7 @@ pub async fn get_user_data_cache(pool: &SqlitePool, key: &str) -> Option<CacheRe
         Err(e) => error!("DB error getting user data cache for {}: {}", key, e),
     }
     
+    USER_DATA_COUNTER.miss();
     None
 }
 
@@ -228,11 +411,12 @@ pub async fn set_user_data_cache(pool: &SqlitePool, key: &str, data: &Value) {
     let data_str = data.to_string();
 
     let result = sqlx::query(
-        "INSERT OR REPLACE INTO user_data_cache (key, data, updated_at, is_dirty) VALUES (?, ?, ?, 0)"
+        "INSERT OR REPLACE INTO user_data_cache (key, data, updated_at, is_dirty, last_accessed_at) VALUES (?, ?, ?, 0, ?)"
     )
     .bind(key)
     .bind(data_str)
     .bind(now)
+    .bind(now)
     .execute(pool)
     .await;
 
@@ -243,6 +427,28 @@ pub async fn set_user_data_cache(pool: &SqlitePool, key: &str, data: &Value) {
     }
 }
 
+pub async fn delete_user_data_cache(pool: &SqlitePool, key: &str) {
+    if let Err(e) = sqlx::query("DELETE FROM user_data_cache WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await
+    {
+        error!("Failed to delete user data cache for {}: {}", key, e);
+    }
+}
+
+// 按前缀批量删除，用于关注关系等会影响多个 key 的写操作
+pub async fn delete_user_data_cache_prefix(pool: &SqlitePool, prefix: &str) {
+    let pattern = format!("{}%", prefix.replace('%', "\\%").replace('_', "\\_"));
+    if let Err(e) = sqlx::query("DELETE FROM user_data_cache WHERE key LIKE ? ESCAPE '\\'")
+        .bind(pattern)
+        .execute(pool)
+        .await
+    {
+        error!("Failed to delete user data cache with prefix {}: {}", prefix, e);
+    }
+}
+
 // 列表API响应缓存操作
 pub async fn get_api_response_cache(pool: &SqlitePool, key: &str) -> Option<Value> {
     let now = get_timestamp();
@@ -258,12 +464,15 @@ pub async fn get_api_response_cache(pool: &SqlitePool, key: &str) -> Option<Valu
             if now > expires_at {
                 info!("🔴 API Cache EXPIRED for {}", key);
                 let _ = sqlx::query("DELETE FROM api_response_cache WHERE key = ?").bind(key).execute(pool).await;
+                API_COUNTER.miss();
                 return None;
             }
             
             let data_str: String = row.get("data");
             if let Ok(json) = serde_json::from_str(&data_str) {
+                touch(pool, "api_response_cache", "key", key, now).await;
                 info!("🟢 API Cache HIT for {}", key);
+                API_COUNTER.hit();
                 return Some(json);
             }
         }
@@ -271,21 +480,27 @@ pub async fn get_api_response_cache(pool: &SqlitePool, key: &str) -> Option<Valu
         Err(e) => error!("DB error getting API cache for {}: {}", key, e),
     }
     
+    API_COUNTER.miss();
     None
 }
 
 pub async fn set_api_response_cache(pool: &SqlitePool, key: &str, data: &Value) {
+    set_api_response_cache_with_ttl(pool, key, data, CACHE_TTL_API).await
+}
+
+pub async fn set_api_response_cache_with_ttl(pool: &SqlitePool, key: &str, data: &Value, ttl_ms: i64) {
     let now = get_timestamp();
-    let expires_at = now + CACHE_TTL_API;
+    let expires_at = now + ttl_ms;
     let data_str = data.to_string();
 
     let result = sqlx::query(
-        "INSERT OR REPLACE INTO api_response_cache (key, data, updated_at, expires_at) VALUES (?, ?, ?, ?)"
+        "INSERT OR REPLACE INTO api_response_cache (key, data, updated_at, expires_at, last_accessed_at) VALUES (?, ?, ?, ?, ?)"
     )
     .bind(key)
     .bind(data_str)
     .bind(now)
     .bind(expires_at)
+    .bind(now)
     .execute(pool)
     .await;
 
@@ -295,3 +510,33 @@ pub async fn set_api_response_cache(pool: &SqlitePool, key: &str, data: &Value)
         info!("💾 API Cache SAVED for {}", key);
     }
 }
+
+// 按前缀列出列表缓存的 key，用于批量修改同一类请求的缓存
+pub async fn get_api_response_cache_keys(pool: &SqlitePool, prefix: &str) -> Vec<String> {
+    let pattern = format!("{}%", prefix.replace('%', "\\%").replace('_', "\\_"));
+    let result = sqlx::query("SELECT key FROM api_response_cache WHERE key LIKE ? ESCAPE '\\'")
+        .bind(pattern)
+        .fetch_all(pool)
+        .await;
+
+    match result {
+        Ok(rows) => rows.iter().map(|row| row.get("key")).collect(),
+        Err(e) => {
+            error!("DB error listing API cache keys for {}: {}", prefix, e);
+            Vec::new()
+        }
+    }
+}
+
+// 只替换数据，保留原有的过期时间
+pub async fn update_api_response_cache_data(pool: &SqlitePool, key: &str, data: &Value) {
+    let result = sqlx::query("UPDATE api_response_cache SET data = ? WHERE key = ?")
+        .bind(data.to_string())
+        .bind(key)
+        .execute(pool)
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to update API cache for {}: {}", key, e);
+    }
+}
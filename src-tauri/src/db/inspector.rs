@@ -0,0 +1,173 @@
+use super::cache::{self, get_timestamp, HitCounter};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+/// 可检查的缓存表
+pub struct CacheTable {
+    pub name: &'static str,
+    pub key_column: &'static str,
+    // 估算单行体积的表达式
+    pub bytes_expr: &'static str,
+    pub has_expires: bool,
+    pub counter: &'static HitCounter,
+}
+
+pub const CACHE_TABLES: [CacheTable; 4] = [
+    CacheTable {
+        name: "media_cache",
+        key_column: "id",
+        bytes_expr: "LENGTH(id) + LENGTH(data)",
+        has_expires: true,
+        counter: &cache::MEDIA_COUNTER,
+    },
+    CacheTable {
+        name: "api_response_cache",
+        key_column: "key",
+        bytes_expr: "LENGTH(key) + LENGTH(data)",
+        has_expires: true,
+        counter: &cache::API_COUNTER,
+    },
+    CacheTable {
+        name: "user_data_cache",
+        key_column: "key",
+        bytes_expr: "LENGTH(key) + LENGTH(data)",
+        has_expires: false,
+        counter: &cache::USER_DATA_COUNTER,
+    },
+    CacheTable {
+        name: "translation_cache",
+        key_column: "id",
+        bytes_expr: "LENGTH(id) + COALESCE(LENGTH(title), 0) + COALESCE(LENGTH(overview), 0) + COALESCE(LENGTH(tagline), 0)",
+        has_expires: true,
+        counter: &cache::TRANSLATION_COUNTER,
+    },
+];
+
+pub fn find_table(name: &str) -> Option<&'static CacheTable> {
+    CACHE_TABLES.iter().find(|t| t.name == name)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheTableStats {
+    pub table: String,
+    pub rows: i64,
+    pub bytes: i64,
+    pub expired: i64,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_ratio: Option<f64>,
+    pub oldest_key: Option<String>,
+    pub oldest_updated_at: Option<i64>,
+    pub newest_key: Option<String>,
+    pub newest_updated_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheKeyInfo {
+    pub key: String,
+    pub bytes: i64,
+    pub updated_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+pub async fn table_stats(pool: &SqlitePool, table: &CacheTable) -> Result<CacheTableStats, sqlx::Error> {
+    let now = get_timestamp();
+    let summary = sqlx::query(&format!(
+        "SELECT COUNT(*) AS row_count, COALESCE(SUM({}), 0) AS bytes FROM {}",
+        table.bytes_expr, table.name
+    ))
+    .fetch_one(pool)
+    .await?;
+
+    let expired: i64 = if table.has_expires {
+        sqlx::query(&format!("SELECT COUNT(*) AS c FROM {} WHERE expires_at < ?", table.name))
+            .bind(now)
+            .fetch_one(pool)
+            .await?
+            .get("c")
+    } else {
+        0
+    };
+
+    let oldest = edge_row(pool, table, "ASC").await?;
+    let newest = edge_row(pool, table, "DESC").await?;
+    let (hits, misses) = table.counter.snapshot();
+
+    Ok(CacheTableStats {
+        table: table.name.to_string(),
+        rows: summary.get("row_count"),
+        bytes: summary.get("bytes"),
+        expired,
+        hits,
+        misses,
+        hit_ratio: if hits + misses > 0 {
+            Some(hits as f64 / (hits + misses) as f64)
+        } else {
+            None
+        },
+        oldest_key: oldest.as_ref().map(|(k, _)| k.clone()),
+        oldest_updated_at: oldest.map(|(_, t)| t),
+        newest_key: newest.as_ref().map(|(k, _)| k.clone()),
+        newest_updated_at: newest.map(|(_, t)| t),
+    })
+}
+
+async fn edge_row(pool: &SqlitePool, table: &CacheTable, order: &str) -> Result<Option<(String, i64)>, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        "SELECT {} AS k, updated_at FROM {} ORDER BY updated_at {} LIMIT 1",
+        table.key_column, table.name, order
+    ))
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| (r.get("k"), r.get("updated_at"))))
+}
+
+fn like_prefix(prefix: &str) -> String {
+    format!("{}%", prefix.replace('%', "\\%").replace('_', "\\_"))
+}
+
+// 按前缀列出 key，最近更新的在前
+pub async fn list_keys(pool: &SqlitePool, table: &CacheTable, prefix: &str, limit: u32) -> Result<Vec<CacheKeyInfo>, sqlx::Error> {
+    let expires = if table.has_expires { "expires_at" } else { "NULL" };
+    let rows = sqlx::query(&format!(
+        "SELECT {key} AS k, {bytes} AS bytes, updated_at, {expires} AS expires_at FROM {table} WHERE {key} LIKE ? ESCAPE '\\' ORDER BY updated_at DESC LIMIT ?",
+        key = table.key_column,
+        bytes = table.bytes_expr,
+        expires = expires,
+        table = table.name
+    ))
+    .bind(like_prefix(prefix))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|r| CacheKeyInfo {
+            key: r.get("k"),
+            bytes: r.get("bytes"),
+            updated_at: r.get("updated_at"),
+            expires_at: r.get("expires_at"),
+        })
+        .collect())
+}
+
+pub async fn purge_prefix(pool: &SqlitePool, table: &CacheTable, prefix: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(&format!("DELETE FROM {} WHERE {} LIKE ? ESCAPE '\\'", table.name, table.key_column))
+        .bind(like_prefix(prefix))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// media_cache 按类型和/或 trakt id 删除
+pub async fn purge_media(pool: &SqlitePool, media_type: Option<&str>, trakt_id: Option<u32>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM media_cache WHERE (? IS NULL OR media_type = ?) AND (? IS NULL OR trakt_id = ?)")
+        .bind(media_type)
+        .bind(media_type)
+        .bind(trakt_id)
+        .bind(trakt_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,374 @@
+use super::cache::{self, get_timestamp};
+use super::DbPool;
+use crate::image_cache::{self, ImageEvictReport};
+use crate::settings::get_app_config;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Row, SqlitePool};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+// 启动后先等一会再执行第一次，避开启动时的请求高峰
+const MAINTENANCE_STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+// 用户数据缓存本身不过期，超过 30 天未更新的直接清理
+const USER_DATA_MAX_AGE: i64 = 30 * 24 * 60 * 60 * 1000;
+const VACUUM_INTERVAL: i64 = 7 * 24 * 60 * 60 * 1000;
+// 空闲页超过总页数的 1/4 时也执行 VACUUM
+const VACUUM_FREELIST_RATIO: f64 = 0.25;
+// 超出体积上限时，每轮淘汰各表最久未访问的 10%
+const SIZE_EVICT_RATIO: f64 = 0.1;
+const SIZE_EVICT_MAX_ROUNDS: u32 = 20;
+
+const LAST_VACUUM_KEY: &str = "cache_last_vacuum_at";
+const REPORT_KEY: &str = "cache_maintenance_report";
+
+// (表名, 主键列)
+const LRU_TABLES: [(&str, &str); 3] = [
+    ("media_cache", "id"),
+    ("api_response_cache", "key"),
+    ("user_data_cache", "key"),
+];
+
+pub struct CacheLimits {
+    pub max_rows: i64,
+    pub max_bytes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TableReport {
+    pub table: String,
+    pub rows_before: i64,
+    pub expired: u64,
+    pub evicted: u64,
+    pub rows_after: i64,
+}
+
+/// 一次缓存维护的结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MaintenanceReport {
+    pub started_at: i64,
+    pub duration_ms: i64,
+    pub tables: Vec<TableReport>,
+    pub translations_expired: u64,
+    pub size_before: i64,
+    pub size_after: i64,
+    pub checkpointed: bool,
+    pub vacuumed: bool,
+    // 图片缓存目录的清理结果
+    #[serde(default)]
+    pub images: ImageEvictReport,
+}
+
+/// 启动后台任务：定期清理过期数据并把缓存控制在上限以内
+pub fn spawn_cache_maintenance_job(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(MAINTENANCE_STARTUP_DELAY).await;
+        loop {
+            if let Err(e) = run_for_app(&app).await {
+                error!("缓存维护失败: {}", e);
+            }
+            tokio::time::sleep(MAINTENANCE_INTERVAL).await;
+        }
+    });
+}
+
+/// 按当前设置执行一次维护，结果保存到 app_config 供设置页展示
+pub async fn run_for_app(app: &AppHandle) -> Result<MaintenanceReport, String> {
+    let pool = match app.try_state::<DbPool>() {
+        Some(pool) => pool.0.clone(),
+        None => return Err("Database not initialized".to_string()),
+    };
+    let conf = get_app_config(app.clone()).await?;
+    let limits = CacheLimits {
+        max_rows: conf.cache_max_rows as i64,
+        max_bytes: conf.cache_max_db_mb as i64 * 1024 * 1024,
+    };
+
+    let mut report = run_maintenance(&pool, &limits).await.map_err(|e| e.to_string())?;
+    match image_cache::evict_for_app(app).await {
+        Ok(images) => report.images = images,
+        Err(e) => error!("图片缓存清理失败: {}", e),
+    }
+    if let Ok(value) = serde_json::to_value(&report) {
+        let _ = cache::set_config(&pool, REPORT_KEY, &value).await;
+    }
+    Ok(report)
+}
+
+pub async fn last_report(pool: &SqlitePool) -> Option<MaintenanceReport> {
+    cache::get_config(pool, REPORT_KEY)
+        .await
+        .and_then(|v| serde_json::from_value(v).ok())
+}
+
+pub async fn run_maintenance(pool: &SqlitePool, limits: &CacheLimits) -> Result<MaintenanceReport, sqlx::Error> {
+    let now = get_timestamp();
+    let mut report = MaintenanceReport {
+        started_at: now,
+        size_before: used_bytes(pool).await?,
+        ..Default::default()
+    };
+
+    // 1. 批量清理过期数据
+    for (table, _) in LRU_TABLES {
+        let rows_before = count_rows(pool, table).await?;
+        let expired = match table {
+            "user_data_cache" => {
+                sqlx::query("DELETE FROM user_data_cache WHERE updated_at < ? AND is_dirty = 0")
+                    .bind(now - USER_DATA_MAX_AGE)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+            _ => {
+                sqlx::query(&format!("DELETE FROM {} WHERE expires_at < ?", table))
+                    .bind(now)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+        };
+        report.tables.push(TableReport {
+            table: table.to_string(),
+            rows_before,
+            expired,
+            ..Default::default()
+        });
+    }
+    report.translations_expired = sqlx::query("DELETE FROM translation_cache WHERE expires_at < ?")
+        .bind(now)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    // 2. 每张表超出行数上限的部分按最近访问时间淘汰
+    for (i, (table, key_column)) in LRU_TABLES.iter().enumerate() {
+        let rows = count_rows(pool, table).await?;
+        if rows > limits.max_rows {
+            report.tables[i].evicted += evict_oldest(pool, table, key_column, rows - limits.max_rows).await?;
+        }
+    }
+
+    // 3. 数据库体积超出上限时继续淘汰
+    let mut rounds = 0;
+    while used_bytes(pool).await? > limits.max_bytes && rounds < SIZE_EVICT_MAX_ROUNDS {
+        let mut evicted = 0;
+        for (i, (table, key_column)) in LRU_TABLES.iter().enumerate() {
+            let rows = count_rows(pool, table).await?;
+            let n = ((rows as f64) * SIZE_EVICT_RATIO).ceil() as i64;
+            if n > 0 {
+                let removed = evict_oldest(pool, table, key_column, n).await?;
+                report.tables[i].evicted += removed;
+                evicted += removed;
+            }
+        }
+        if evicted == 0 {
+            break;
+        }
+        rounds += 1;
+    }
+
+    for (i, (table, _)) in LRU_TABLES.iter().enumerate() {
+        report.tables[i].rows_after = count_rows(pool, table).await?;
+    }
+
+    // 4. 合并 WAL，必要时 VACUUM 回收空间
+    report.checkpointed = pool.execute("PRAGMA wal_checkpoint(TRUNCATE)").await.is_ok();
+
+    // 第一次维护只记录时间，不因为没有记录就立即 VACUUM
+    let last_vacuum = match cache::get_config(pool, LAST_VACUUM_KEY).await.and_then(|v| v.as_i64()) {
+        Some(last_vacuum) => last_vacuum,
+        None => {
+            let _ = cache::set_config(pool, LAST_VACUUM_KEY, &serde_json::json!(now)).await;
+            now
+        }
+    };
+    let (page_count, freelist_count) = page_stats(pool).await?;
+    let fragmented = page_count > 0 && (freelist_count as f64 / page_count as f64) > VACUUM_FREELIST_RATIO;
+    if fragmented || now - last_vacuum > VACUUM_INTERVAL {
+        match pool.execute("VACUUM").await {
+            Ok(_) => {
+                report.vacuumed = true;
+                let _ = cache::set_config(pool, LAST_VACUUM_KEY, &serde_json::json!(now)).await;
+            }
+            Err(e) => error!("VACUUM failed: {}", e),
+        }
+    }
+
+    report.size_after = used_bytes(pool).await?;
+    report.duration_ms = get_timestamp() - now;
+
+    info!(
+        "Cache maintenance done in {}ms: {} -> {} bytes, expired {:?}, evicted {:?}, vacuumed: {}",
+        report.duration_ms,
+        report.size_before,
+        report.size_after,
+        report.tables.iter().map(|t| (t.table.as_str(), t.expired)).collect::<Vec<_>>(),
+        report.tables.iter().map(|t| (t.table.as_str(), t.evicted)).collect::<Vec<_>>(),
+        report.vacuumed
+    );
+    Ok(report)
+}
+
+async fn count_rows(pool: &SqlitePool, table: &str) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query(&format!("SELECT COUNT(*) AS c FROM {}", table))
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("c"))
+}
+
+async fn evict_oldest(pool: &SqlitePool, table: &str, key_column: &str, n: i64) -> Result<u64, sqlx::Error> {
+    let sql = format!(
+        "DELETE FROM {table} WHERE {key} IN (SELECT {key} FROM {table} ORDER BY last_accessed_at ASC LIMIT ?)",
+        table = table,
+        key = key_column
+    );
+    Ok(sqlx::query(&sql).bind(n).execute(pool).await?.rows_affected())
+}
+
+async fn page_stats(pool: &SqlitePool) -> Result<(i64, i64), sqlx::Error> {
+    let page_count: i64 = sqlx::query("PRAGMA page_count").fetch_one(pool).await?.get(0);
+    let freelist_count: i64 = sqlx::query("PRAGMA freelist_count").fetch_one(pool).await?.get(0);
+    Ok((page_count, freelist_count))
+}
+
+// 实际占用的体积，不计入空闲页
+async fn used_bytes(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let page_size: i64 = sqlx::query("PRAGMA page_size").fetch_one(pool).await?.get(0);
+    let (page_count, freelist_count) = page_stats(pool).await?;
+    Ok((page_count - freelist_count) * page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::run_migrations;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const NO_LIMITS: CacheLimits = CacheLimits {
+        max_rows: i64::MAX,
+        max_bytes: i64::MAX,
+    };
+
+    async fn memory_pool() -> SqlitePool {
+        // 内存数据库每个连接独立，只用一个连接
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        run_migrations(&pool).await.unwrap();
+        pool
+    }
+
+    async fn insert_api_cache(pool: &SqlitePool, key: &str, expires_at: i64, last_accessed_at: i64) {
+        sqlx::query("INSERT INTO api_response_cache (key, data, updated_at, expires_at, last_accessed_at) VALUES (?, '{}', 0, ?, ?)")
+            .bind(key)
+            .bind(expires_at)
+            .bind(last_accessed_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn api_cache_keys(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query("SELECT key FROM api_response_cache ORDER BY key")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get("key"))
+            .collect()
+    }
+
+    fn table_report<'a>(report: &'a MaintenanceReport, table: &str) -> &'a TableReport {
+        report.tables.iter().find(|t| t.table == table).unwrap()
+    }
+
+    #[test]
+    fn expired_rows_are_purged() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+            let now = get_timestamp();
+            insert_api_cache(&pool, "expired", now - 1000, now).await;
+            insert_api_cache(&pool, "fresh", now + 60_000, now).await;
+            // 用户数据按更新时间清理，未同步的保留
+            let old = now - USER_DATA_MAX_AGE - 1000;
+            for (key, dirty) in [("old_clean", 0), ("old_dirty", 1)] {
+                sqlx::query("INSERT INTO user_data_cache (key, data, updated_at, is_dirty, last_accessed_at) VALUES (?, '{}', ?, ?, ?)")
+                    .bind(key)
+                    .bind(old)
+                    .bind(dirty)
+                    .bind(now)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+
+            let report = run_maintenance(&pool, &NO_LIMITS).await.unwrap();
+
+            assert_eq!(api_cache_keys(&pool).await, vec!["fresh"]);
+            assert_eq!(table_report(&report, "api_response_cache").expired, 1);
+            let user_data = table_report(&report, "user_data_cache");
+            assert_eq!((user_data.expired, user_data.rows_after), (1, 1));
+        });
+    }
+
+    #[test]
+    fn rows_over_limit_are_evicted_least_recently_used_first() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+            let expires_at = get_timestamp() + 60_000;
+            for (key, accessed) in [("a", 4), ("b", 1), ("c", 3), ("d", 2)] {
+                insert_api_cache(&pool, key, expires_at, accessed).await;
+            }
+
+            let limits = CacheLimits {
+                max_rows: 2,
+                max_bytes: i64::MAX,
+            };
+            let report = run_maintenance(&pool, &limits).await.unwrap();
+
+            assert_eq!(api_cache_keys(&pool).await, vec!["a", "c"]);
+            let api = table_report(&report, "api_response_cache");
+            assert_eq!((api.evicted, api.rows_after), (2, 2));
+        });
+    }
+
+    #[test]
+    fn size_limit_keeps_evicting_until_nothing_is_left() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+            let expires_at = get_timestamp() + 60_000;
+            for i in 0..10 {
+                insert_api_cache(&pool, &format!("key_{}", i), expires_at, i).await;
+            }
+
+            // 体积上限达不到时每轮淘汰 10%，直到没有可以淘汰的
+            let limits = CacheLimits {
+                max_rows: i64::MAX,
+                max_bytes: 0,
+            };
+            let report = run_maintenance(&pool, &limits).await.unwrap();
+
+            assert!(api_cache_keys(&pool).await.is_empty());
+            assert_eq!(table_report(&report, "api_response_cache").evicted, 10);
+        });
+    }
+
+    #[test]
+    fn first_run_records_vacuum_time_instead_of_vacuuming() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+
+            let report = run_maintenance(&pool, &NO_LIMITS).await.unwrap();
+            assert!(!report.vacuumed);
+            assert!(cache::get_config(&pool, LAST_VACUUM_KEY).await.is_some());
+
+            let _ = cache::set_config(&pool, LAST_VACUUM_KEY, &serde_json::json!(0)).await;
+            let report = run_maintenance(&pool, &NO_LIMITS).await.unwrap();
+            assert!(report.vacuumed);
+        });
+    }
+}
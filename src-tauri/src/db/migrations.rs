@@ -0,0 +1,272 @@
+use super::cache::get_timestamp;
+use super::schema;
+use log::info;
+use sqlx::{Executor, Row, SqlitePool};
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+// 按版本号升序排列，只能追加，已发布的版本不要修改
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        sql: schema::FULL_SCHEMA,
+    },
+    Migration {
+        version: 2,
+        description: "index cache timestamps for cleanup",
+        sql: "
+CREATE INDEX IF NOT EXISTS idx_user_data_updated ON user_data_cache(updated_at);
+CREATE INDEX IF NOT EXISTS idx_translation_expires ON translation_cache(expires_at);
+",
+    },
+    Migration {
+        version: 3,
+        description: "track last access time for LRU eviction",
+        sql: "
+ALTER TABLE media_cache ADD COLUMN last_accessed_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE api_response_cache ADD COLUMN last_accessed_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE user_data_cache ADD COLUMN last_accessed_at INTEGER NOT NULL DEFAULT 0;
+UPDATE media_cache SET last_accessed_at = updated_at;
+UPDATE api_response_cache SET last_accessed_at = updated_at;
+UPDATE user_data_cache SET last_accessed_at = updated_at;
+CREATE INDEX IF NOT EXISTS idx_media_accessed ON media_cache(last_accessed_at);
+CREATE INDEX IF NOT EXISTS idx_api_accessed ON api_response_cache(last_accessed_at);
+CREATE INDEX IF NOT EXISTS idx_user_data_accessed ON user_data_cache(last_accessed_at);
+",
+    },
+    Migration {
+        version: 4,
+        description: "record which user a mirrored note was listed for",
+        sql: "
+ALTER TABLE notes ADD COLUMN owner TEXT NOT NULL DEFAULT 'me';
+CREATE INDEX IF NOT EXISTS idx_notes_owner ON notes(owner, media_type);
+",
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query("SELECT COALESCE(MAX(version), 0) AS version FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("version"))
+}
+
+/// 执行所有未执行的迁移，每个版本一个事务，返回执行后的版本号
+/// 没有 schema_version 表的旧数据库从版本 1 开始，基线语句都是 IF NOT EXISTS，可以安全重放
+pub async fn run_migrations(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    pool.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+    )
+    .await?;
+
+    let mut version = current_version(pool).await?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+
+    for migration in pending {
+        info!("Applying migration {}: {}", migration.version, migration.description);
+        let mut tx = pool.begin().await?;
+        tx.execute(migration.sql).await?;
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(get_timestamp())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    // 迁移机制之前发布的安装由这份表结构创建 (没有笔记表)，冻结在这里，不随 schema.rs 变化
+    const LEGACY_SCHEMA: &str = "
+-- 媒体详情缓存
+CREATE TABLE IF NOT EXISTS media_cache (
+    id TEXT PRIMARY KEY,
+    media_type TEXT NOT NULL,
+    trakt_id INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+-- 列表请求缓存
+CREATE TABLE IF NOT EXISTS api_response_cache (
+    key TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+-- 用户数据缓存
+CREATE TABLE IF NOT EXISTS user_data_cache (
+    key TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    is_dirty BOOLEAN DEFAULT 0
+);
+
+-- 翻译缓存
+CREATE TABLE IF NOT EXISTS translation_cache (
+    id TEXT PRIMARY KEY,
+    title TEXT,
+    overview TEXT,
+    tagline TEXT,
+    updated_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+-- 应用配置表
+CREATE TABLE IF NOT EXISTS app_config (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- 索引
+CREATE INDEX IF NOT EXISTS idx_media_expires ON media_cache(expires_at);
+CREATE INDEX IF NOT EXISTS idx_api_expires ON api_response_cache(expires_at);
+";
+
+    async fn memory_pool() -> SqlitePool {
+        // 内存数据库每个连接独立，只用一个连接
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn table_exists(pool: &SqlitePool, name: &str) -> bool {
+        sqlx::query("SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    async fn index_exists(pool: &SqlitePool, name: &str) -> bool {
+        sqlx::query("SELECT name FROM sqlite_master WHERE type = 'index' AND name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[test]
+    fn versions_are_strictly_increasing() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[test]
+    fn fresh_database_migrates_to_latest() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+            let version = run_migrations(&pool).await.unwrap();
+
+            assert_eq!(version, latest_version());
+            assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+            for table in ["media_cache", "api_response_cache", "user_data_cache", "translation_cache", "app_config", "notes"] {
+                assert!(table_exists(&pool, table).await, "missing table {}", table);
+            }
+        });
+    }
+
+    #[test]
+    fn legacy_database_upgrades_and_keeps_data() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+            // 模拟迁移机制之前的安装：只执行过当时的建表语句
+            pool.execute(LEGACY_SCHEMA).await.unwrap();
+            sqlx::query("INSERT INTO media_cache (id, media_type, trakt_id, data, updated_at, expires_at) VALUES ('movie_1', 'movie', 1, '{}', 1, 2)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO app_config (key, value, updated_at) VALUES ('token', '{}', 1)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert!(!table_exists(&pool, "schema_version").await);
+
+            let version = run_migrations(&pool).await.unwrap();
+            assert_eq!(version, latest_version());
+            assert!(index_exists(&pool, "idx_user_data_updated").await);
+            assert!(index_exists(&pool, "idx_media_accessed").await);
+            // 笔记表由重放基线创建，随后的迁移补上 owner 列
+            assert!(table_exists(&pool, "notes").await);
+            assert!(index_exists(&pool, "idx_notes_owner").await);
+            sqlx::query("INSERT INTO notes (id, media_type, trakt_id, notes, updated_at) VALUES (1, 'movie', 1, 'note', 1)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            let owner: String = sqlx::query("SELECT owner FROM notes WHERE id = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .get("owner");
+            assert_eq!(owner, "me");
+
+            // 旧数据的最近访问时间取自 updated_at
+            let accessed: i64 = sqlx::query("SELECT last_accessed_at FROM media_cache WHERE id = 'movie_1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .get("last_accessed_at");
+            assert_eq!(accessed, 1);
+
+            let count: i64 = sqlx::query("SELECT COUNT(*) AS c FROM media_cache")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .get("c");
+            assert_eq!(count, 1);
+            let token: String = sqlx::query("SELECT value FROM app_config WHERE key = 'token'")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .get("value");
+            assert_eq!(token, "{}");
+        });
+    }
+
+    #[test]
+    fn running_twice_is_a_no_op() {
+        tauri::async_runtime::block_on(async {
+            let pool = memory_pool().await;
+            run_migrations(&pool).await.unwrap();
+            let version = run_migrations(&pool).await.unwrap();
+
+            assert_eq!(version, latest_version());
+            let applied: i64 = sqlx::query("SELECT COUNT(*) AS c FROM schema_version")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .get("c");
+            assert_eq!(applied, MIGRATIONS.len() as i64);
+        });
+    }
+}
@@ -1,21 +1,19 @@
 pub mod schema;
 pub mod cache;
+pub mod inspector;
+pub mod maintenance;
+pub mod migrations;
+pub mod notes;
+pub mod policy;
 
 use tauri::{AppHandle, Manager};
-use tauri_plugin_sql::{Migration, MigrationKind};
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions, SqliteConnectOptions};
-use sqlx::{Executor, Row};
 use std::path::PathBuf;
 use std::str::FromStr;
 use log::{info, error};
 
 pub struct DbPool(pub SqlitePool);
 
-// 保留此函数是为了兼容 tauri-plugin-sql 的接口，防止编译错误，但实际上我们不再依赖它来建表
-pub fn get_migrations() -> Vec<Migration> {
-    vec![] 
-}
-
 pub async fn init_db_pool(app: &AppHandle) -> Result<SqlitePool, Box<dyn std::error::Error>> {
     let app_dir = app.path().app_data_dir()?;
     let db_path = app_dir.join("mytv.db");
@@ -39,13 +37,15 @@ pub async fn init_db_pool(app: &AppHandle) -> Result<SqlitePool, Box<dyn std::er
         .connect_with(options)
         .await?;
         
-    // 手动执行建表语句，确保表一定存在！
-    info!("Executing DB schema initialization...");
-    if let Err(e) = pool.execute(schema::FULL_SCHEMA).await {
-        error!("Failed to execute schema: {}", e);
-        return Err(Box::new(e));
+    // 按版本依次执行迁移，旧安装会从当前版本升级到最新
+    info!("Running DB migrations...");
+    match migrations::run_migrations(&pool).await {
+        Ok(version) => info!("DB schema at version {} (latest {})", version, migrations::latest_version()),
+        Err(e) => {
+            error!("Failed to run migrations: {}", e);
+            return Err(Box::new(e));
+        }
     }
-    info!("DB schema initialized successfully.");
         
     Ok(pool)
 }
@@ -0,0 +1,149 @@
+use super::cache::get_timestamp;
+use crate::model::notes::{LocalNote, Note};
+use log::error;
+use serde_json::Value;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+
+// 笔记所在的条目，以及挂在哪条观看记录 / 收藏 / 评分上
+pub struct NoteTarget<'a> {
+    pub media_type: &'a str,
+    pub trakt_id: u32,
+    pub attached_to: Option<&'a str>,
+    pub attached_id: Option<u64>,
+}
+
+// 写入或覆盖一条笔记，owner 为列出该笔记时使用的用户 id，自己新增的为 me
+pub async fn upsert_note(pool: &SqlitePool, owner: &str, note: &Note, target: &NoteTarget<'_>, item: Option<&Value>) {
+    let result = sqlx::query(
+        "INSERT OR REPLACE INTO notes (id, owner, media_type, trakt_id, attached_to, attached_id, notes, spoiler, privacy, item, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(note.id as i64)
+    .bind(owner)
+    .bind(target.media_type)
+    .bind(target.trakt_id)
+    .bind(target.attached_to)
+    .bind(target.attached_id.map(|id| id as i64))
+    .bind(&note.notes)
+    .bind(note.spoiler)
+    .bind(&note.privacy)
+    .bind(item.map(|v| v.to_string()))
+    .bind(get_timestamp())
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to save note {}: {}", note.id, e);
+    }
+}
+
+// 编辑笔记时 Trakt 只返回笔记本身，保留本地已有的条目信息
+pub async fn update_note_content(pool: &SqlitePool, note: &Note) {
+    let result = sqlx::query("UPDATE notes SET notes = ?, spoiler = ?, privacy = ?, updated_at = ? WHERE id = ?")
+        .bind(&note.notes)
+        .bind(note.spoiler)
+        .bind(&note.privacy)
+        .bind(get_timestamp())
+        .bind(note.id as i64)
+        .execute(pool)
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to update note {}: {}", note.id, e);
+    }
+}
+
+pub async fn delete_note(pool: &SqlitePool, id: u64) {
+    if let Err(e) = sqlx::query("DELETE FROM notes WHERE id = ?")
+        .bind(id as i64)
+        .execute(pool)
+        .await
+    {
+        error!("Failed to delete note {}: {}", id, e);
+    }
+}
+
+// 完整列出某个用户的笔记后，删除本地有但 Trakt 上已经不存在的 (在别处删除的)
+// media_type 为空时处理该用户的全部类型
+pub async fn prune_notes(pool: &SqlitePool, owner: &str, media_type: Option<&str>, keep_ids: &[u64]) -> u64 {
+    let keep = serde_json::to_string(keep_ids).unwrap_or_else(|_| "[]".to_string());
+    let result = sqlx::query(
+        "DELETE FROM notes WHERE owner = ? AND (? IS NULL OR media_type = ?) AND id NOT IN (SELECT value FROM json_each(?))"
+    )
+    .bind(owner)
+    .bind(media_type)
+    .bind(media_type)
+    .bind(keep)
+    .execute(pool)
+    .await;
+
+    match result {
+        Ok(res) => res.rows_affected(),
+        Err(e) => {
+            error!("Failed to prune notes for {}: {}", owner, e);
+            0
+        }
+    }
+}
+
+// LIKE 的通配符和转义符本身都按普通字符匹配
+fn like_contains(query: &str) -> String {
+    let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+// 按内容搜索本地笔记，media_type 为空时搜索全部
+pub async fn search_notes(pool: &SqlitePool, query: &str, media_type: Option<&str>) -> Vec<LocalNote> {
+    let pattern = like_contains(query);
+    let result = sqlx::query(
+        "SELECT * FROM notes WHERE notes LIKE ? ESCAPE '\\' AND (? IS NULL OR media_type = ?) ORDER BY updated_at DESC"
+    )
+    .bind(pattern)
+    .bind(media_type)
+    .bind(media_type)
+    .fetch_all(pool)
+    .await;
+
+    match result {
+        Ok(rows) => rows.iter().map(row_to_note).collect(),
+        Err(e) => {
+            error!("DB error searching notes: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// 获取某个条目上的全部笔记
+pub async fn get_notes_for_item(pool: &SqlitePool, media_type: &str, trakt_id: u32) -> Vec<LocalNote> {
+    let result = sqlx::query("SELECT * FROM notes WHERE media_type = ? AND trakt_id = ? ORDER BY updated_at DESC")
+        .bind(media_type)
+        .bind(trakt_id)
+        .fetch_all(pool)
+        .await;
+
+    match result {
+        Ok(rows) => rows.iter().map(row_to_note).collect(),
+        Err(e) => {
+            error!("DB error getting notes for {}_{}: {}", media_type, trakt_id, e);
+            Vec::new()
+        }
+    }
+}
+
+fn row_to_note(row: &SqliteRow) -> LocalNote {
+    let id: i64 = row.get("id");
+    let attached_id: Option<i64> = row.get("attached_id");
+    let item: Option<String> = row.get("item");
+    LocalNote {
+        id: id as u64,
+        media_type: row.get("media_type"),
+        trakt_id: row.get("trakt_id"),
+        attached_to: row.get("attached_to"),
+        attached_id: attached_id.map(|id| id as u64),
+        notes: row.get("notes"),
+        spoiler: row.get("spoiler"),
+        privacy: row.get("privacy"),
+        item: item.and_then(|s| serde_json::from_str(&s).ok()),
+        updated_at: row.get("updated_at"),
+    }
+}
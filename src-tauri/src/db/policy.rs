@@ -0,0 +1,265 @@
+use super::cache;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+pub const MEDIA_UPDATE_EVENT: &str = "media-update";
+pub const USER_DATA_UPDATE_EVENT: &str = "user-data-update";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStore {
+    Media,
+    ApiResponse,
+    UserData,
+}
+
+/// 一个接口的缓存策略：存在哪张表、多久过期、多久后视为陈旧、刷新后发什么事件
+pub struct CachePolicy {
+    // 设置中覆盖 TTL 时使用的名字
+    pub name: &'static str,
+    pub store: CacheStore,
+    pub ttl_ms: i64,
+    pub stale_ms: i64,
+    pub event: &'static str,
+}
+
+/// 设置中对某个策略的 TTL 覆盖，为空的字段使用默认值
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TtlOverride {
+    pub ttl_ms: Option<i64>,
+    pub stale_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    Fresh,
+    // 可以先返回，同时在后台刷新
+    Stale,
+    // 不能再使用，必须重新请求
+    Expired,
+}
+
+pub const MOVIE_DETAILS: CachePolicy = CachePolicy {
+    name: "movie_details",
+    store: CacheStore::Media,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_TRACKED,
+    event: MEDIA_UPDATE_EVENT,
+};
+
+pub const USER_PROFILE: CachePolicy = CachePolicy {
+    name: "user_profile",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const USER_WATCHED: CachePolicy = CachePolicy {
+    name: "user_watched",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const USER_HISTORY: CachePolicy = CachePolicy {
+    name: "user_history",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const UP_NEXT: CachePolicy = CachePolicy {
+    name: "up_next",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const USER_STATS: CachePolicy = CachePolicy {
+    name: "user_stats",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const USER_COLLECTION: CachePolicy = CachePolicy {
+    name: "user_collection",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const USER_WATCHLIST: CachePolicy = CachePolicy {
+    name: "user_watchlist",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const PLAYBACK: CachePolicy = CachePolicy {
+    name: "playback",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+// 公开资料、关注/粉丝/好友列表和关注请求共用
+pub const SOCIAL: CachePolicy = CachePolicy {
+    name: "social",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const FRIENDS_ACTIVITY: CachePolicy = CachePolicy {
+    name: "friends_activity",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const HIDDEN_ITEMS: CachePolicy = CachePolicy {
+    name: "hidden_items",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const MISSING_EPISODES: CachePolicy = CachePolicy {
+    name: "missing_episodes",
+    store: CacheStore::UserData,
+    ttl_ms: cache::CACHE_TTL_LONG,
+    stale_ms: cache::STALE_WHILE_REVALIDATE_USER,
+    event: USER_DATA_UPDATE_EVENT,
+};
+
+pub const POLICIES: [&CachePolicy; 13] = [
+    &MOVIE_DETAILS,
+    &USER_PROFILE,
+    &USER_WATCHED,
+    &USER_HISTORY,
+    &UP_NEXT,
+    &USER_STATS,
+    &USER_COLLECTION,
+    &USER_WATCHLIST,
+    &PLAYBACK,
+    &SOCIAL,
+    &FRIENDS_ACTIVITY,
+    &HIDDEN_ITEMS,
+    &MISSING_EPISODES,
+];
+
+pub fn find_policy(name: &str) -> Option<&'static CachePolicy> {
+    POLICIES.iter().copied().find(|p| p.name == name)
+}
+
+lazy_static! {
+    // 启动时从设置中加载，修改设置时同步更新
+    static ref TTL_OVERRIDES: RwLock<HashMap<String, TtlOverride>> = RwLock::new(HashMap::new());
+}
+
+pub fn set_overrides(overrides: HashMap<String, TtlOverride>) {
+    if let Ok(mut guard) = TTL_OVERRIDES.write() {
+        *guard = overrides;
+    }
+}
+
+impl CachePolicy {
+    /// 应用覆盖后的 (ttl, stale)
+    pub fn effective_with(&self, ttl_override: Option<&TtlOverride>) -> (i64, i64) {
+        let ttl = ttl_override.and_then(|o| o.ttl_ms).unwrap_or(self.ttl_ms);
+        let stale = ttl_override.and_then(|o| o.stale_ms).unwrap_or(self.stale_ms);
+        (ttl, stale)
+    }
+
+    pub fn effective(&self) -> (i64, i64) {
+        let guard = TTL_OVERRIDES.read().ok();
+        self.effective_with(guard.as_ref().and_then(|g| g.get(self.name)))
+    }
+
+    /// 判断一条缓存的新鲜程度
+    /// expires_at 在写入时按当时的 ttl 计算，修改 ttl 后从下一次写入开始生效；
+    /// 没有 expires_at 的表 (user_data_cache) 按 updated_at + ttl 计算
+    pub fn freshness_with(&self, ttl_ms: i64, stale_ms: i64, updated_at: i64, expires_at: Option<i64>, now: i64) -> Freshness {
+        let expires_at = expires_at.unwrap_or(updated_at + ttl_ms);
+        if now > expires_at {
+            Freshness::Expired
+        } else if updated_at + stale_ms < now {
+            Freshness::Stale
+        } else {
+            Freshness::Fresh
+        }
+    }
+
+    pub fn freshness(&self, updated_at: i64, expires_at: Option<i64>, now: i64) -> Freshness {
+        let (ttl, stale) = self.effective();
+        self.freshness_with(ttl, stale, updated_at, expires_at, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    const TEST_POLICY: CachePolicy = CachePolicy {
+        name: "test",
+        store: CacheStore::UserData,
+        ttl_ms: 24 * HOUR,
+        stale_ms: HOUR,
+        event: USER_DATA_UPDATE_EVENT,
+    };
+
+    #[test]
+    fn freshness_follows_stale_window_and_ttl() {
+        let now = 100 * HOUR;
+        let (ttl, stale) = TEST_POLICY.effective_with(None);
+
+        assert_eq!(TEST_POLICY.freshness_with(ttl, stale, now - HOUR / 2, None, now), Freshness::Fresh);
+        assert_eq!(TEST_POLICY.freshness_with(ttl, stale, now - 2 * HOUR, None, now), Freshness::Stale);
+        assert_eq!(TEST_POLICY.freshness_with(ttl, stale, now - 25 * HOUR, None, now), Freshness::Expired);
+    }
+
+    #[test]
+    fn stored_expiry_wins_over_ttl() {
+        let now = 100 * HOUR;
+        let (ttl, stale) = TEST_POLICY.effective_with(None);
+
+        // 被标记为陈旧的媒体缓存 updated_at 为 0，但未到 expires_at，仍可使用
+        assert_eq!(TEST_POLICY.freshness_with(ttl, stale, 0, Some(now + HOUR), now), Freshness::Stale);
+        assert_eq!(TEST_POLICY.freshness_with(ttl, stale, now, Some(now - 1), now), Freshness::Expired);
+    }
+
+    #[test]
+    fn overrides_replace_only_given_fields() {
+        let ttl_override = TtlOverride { ttl_ms: None, stale_ms: Some(5 * HOUR) };
+        assert_eq!(TEST_POLICY.effective_with(Some(&ttl_override)), (24 * HOUR, 5 * HOUR));
+
+        let ttl_override = TtlOverride { ttl_ms: Some(2 * HOUR), stale_ms: None };
+        assert_eq!(TEST_POLICY.effective_with(Some(&ttl_override)), (2 * HOUR, HOUR));
+    }
+
+    #[test]
+    fn policy_names_are_unique() {
+        for (i, a) in POLICIES.iter().enumerate() {
+            for b in POLICIES.iter().skip(i + 1) {
+                assert_ne!(a.name, b.name);
+            }
+            assert!(find_policy(a.name).is_some());
+        }
+    }
+}
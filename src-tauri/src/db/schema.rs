@@ -1,3 +1,5 @@
+// 版本 1 的基线表结构，已发布的安装都由它创建
+// 不要再修改这里，表结构变化请在 migrations.rs 中追加新版本
 pub const FULL_SCHEMA: &str = "
 -- 媒体详情缓存
 CREATE TABLE IF NOT EXISTS media_cache (
@@ -42,7 +44,22 @@ CREATE TABLE IF NOT EXISTS app_config (
     updated_at INTEGER NOT NULL
 );
 
+-- 笔记本地镜像
+CREATE TABLE IF NOT EXISTS notes (
+    id INTEGER PRIMARY KEY,
+    media_type TEXT NOT NULL,
+    trakt_id INTEGER NOT NULL,
+    attached_to TEXT,
+    attached_id INTEGER,
+    notes TEXT NOT NULL,
+    spoiler BOOLEAN DEFAULT 0,
+    privacy TEXT,
+    item TEXT,
+    updated_at INTEGER NOT NULL
+);
+
 -- 索引
 CREATE INDEX IF NOT EXISTS idx_media_expires ON media_cache(expires_at);
 CREATE INDEX IF NOT EXISTS idx_api_expires ON api_response_cache(expires_at);
+CREATE INDEX IF NOT EXISTS idx_notes_media ON notes(media_type, trakt_id);
 ";
@@ -0,0 +1,427 @@
+use crate::settings::get_app_config;
+use crate::trakt_api::single_flight::SingleFlight;
+use lazy_static::lazy_static;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+pub mod prefetch;
+mod variant;
+
+pub use variant::ImageVariant;
+
+/// 前端通过这个协议直接加载缓存的图片
+pub const IMAGE_SCHEME: &str = "mytv-img";
+
+const CACHE_DIR_NAME: &str = "image_cache";
+// 地址到内容哈希的索引，每个地址一个小文件
+const INDEX_DIR_NAME: &str = "index";
+// 单张图片的上限
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+// 只代理这些域名 (及其子域名) 的图片，避免被当成开放代理
+const ALLOWED_HOSTS: [&str; 3] = ["trakt.tv", "image.tmdb.org", "gravatar.com"];
+// 淘汰时清理到上限的 90%，避免每次写入都触发
+const EVICT_TARGET_RATIO: f64 = 0.9;
+// 新写入超过这么多字节后检查一次总体积
+const EVICT_CHECK_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_REDIRECTS: usize = 5;
+
+lazy_static! {
+    // 同一个地址同时只下载一次，同一个尺寸同时只生成一次
+    static ref PENDING: SingleFlight<Result<String, String>> = SingleFlight::new();
+    static ref WRITTEN_SINCE_CHECK: AtomicU64 = AtomicU64::new(0);
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ImageEvictReport {
+    pub files_before: u64,
+    pub bytes_before: u64,
+    pub files_removed: u64,
+    pub bytes_removed: u64,
+}
+
+pub fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join(CACHE_DIR_NAME);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// 检查图片地址：补全协议、只允许 https 和白名单域名
+pub fn validate_url(raw: &str) -> Result<url::Url, String> {
+    let raw = raw.trim();
+    let normalized = if raw.starts_with("http://") {
+        raw.replacen("http://", "https://", 1)
+    } else if raw.starts_with("https://") {
+        raw.to_string()
+    } else {
+        format!("https://{}", raw)
+    };
+
+    let url = url::Url::parse(&normalized).map_err(|e| e.to_string())?;
+    let host = url.host_str().ok_or("Image url has no host")?.to_lowercase();
+    let allowed = ALLOWED_HOSTS
+        .iter()
+        .any(|allowed| host == *allowed || host.ends_with(&format!(".{}", allowed)));
+    if !allowed {
+        return Err(format!("Image host not allowed: {}", host));
+    }
+    Ok(url)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 按内容寻址：原图文件名为图片内容的 sha256，不同地址的相同图片只存一份
+fn content_key(bytes: &[u8]) -> String {
+    sha256_hex(bytes)
+}
+
+// 索引文件名为地址的 sha256，内容为对应的原图 key
+fn url_key(url: &url::Url) -> String {
+    sha256_hex(url.as_str().as_bytes())
+}
+
+fn index_dir(dir: &Path) -> PathBuf {
+    dir.join(INDEX_DIR_NAME)
+}
+
+// 查找地址对应的原图，索引指向的文件已被淘汰时视为未缓存
+fn lookup(dir: &Path, url_key: &str) -> Option<String> {
+    let key = fs::read_to_string(index_dir(dir).join(url_key)).ok()?;
+    let key = key.trim();
+    if is_file_key(key) && !key.contains('_') && dir.join(key).exists() {
+        Some(key.to_string())
+    } else {
+        None
+    }
+}
+
+// 保存下载的原图并记录索引，返回原图 key 以及是否新写入了文件
+fn store(dir: &Path, url_key: &str, bytes: &[u8]) -> Result<(String, bool), String> {
+    let key = content_key(bytes);
+    let path = dir.join(&key);
+    let written = if path.exists() {
+        touch(&path);
+        false
+    } else {
+        write_atomic(dir, &key, bytes)?;
+        true
+    };
+
+    let index = index_dir(dir);
+    fs::create_dir_all(&index).map_err(|e| e.to_string())?;
+    write_atomic(&index, url_key, key.as_bytes())?;
+    Ok((key, written))
+}
+
+// 原图为 64 位十六进制哈希，其它尺寸为 哈希_尺寸名
+fn is_file_key(key: &str) -> bool {
+    let (hash, variant) = match key.split_once('_') {
+        Some((hash, name)) => (hash, ImageVariant::from_name(name)),
+        None => (key, Some(ImageVariant::Original)),
+    };
+    variant.is_some() && hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+}
+
+/// 根据文件头判断图片类型，只接受能解码生成其它尺寸的格式，其它返回 None
+pub fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// 前端可直接使用的地址，各平台的自定义协议格式不同
+pub fn scheme_url(key: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}", IMAGE_SCHEME, key)
+    } else {
+        format!("{}://localhost/{}", IMAGE_SCHEME, key)
+    }
+}
+
+/// 返回图片在本地缓存中的 key，未缓存时先下载
+pub async fn get_or_fetch(app: &AppHandle, raw_url: &str) -> Result<String, String> {
+    let url = validate_url(raw_url)?;
+    let url_key = url_key(&url);
+    let dir = cache_dir(app)?;
+
+    if let Some(key) = lookup(&dir, &url_key) {
+        touch(&dir.join(&key));
+        return Ok(key);
+    }
+
+    PENDING
+        .run(format!("url_{}", url_key), || async {
+            let bytes = download(&url).await?;
+            let (key, written) = store(&dir, &url_key, &bytes)?;
+            if written {
+                info!("Image cached: {} ({} bytes)", url, bytes.len());
+                record_written(app, bytes.len());
+            } else {
+                info!("Image {} already cached as {}", url, key);
+            }
+            Ok(key)
+        })
+        .await
+}
+
+/// 返回指定尺寸的缓存 key，没有时从原图生成
+pub async fn get_or_render(app: &AppHandle, raw_url: &str, variant: ImageVariant) -> Result<String, String> {
+    let original_key = get_or_fetch(app, raw_url).await?;
+    if variant == ImageVariant::Original {
+        return Ok(original_key);
+    }
+
+    let dir = cache_dir(app)?;
+    let key = variant.file_key(&original_key);
+    let path = dir.join(&key);
+    if path.exists() {
+        touch(&path);
+        return Ok(key);
+    }
+
+    PENDING
+        .run(key.clone(), || async {
+            let source = dir.join(&original_key);
+            // 解码和缩放比较耗 CPU，放到阻塞线程池
+            let bytes = tauri::async_runtime::spawn_blocking(move || {
+                let original = fs::read(&source).map_err(|e| e.to_string())?;
+                variant::render(&original, variant)
+            })
+            .await
+            .map_err(|e| e.to_string())??;
+            write_atomic(&dir, &key, &bytes)?;
+            record_written(app, bytes.len());
+            Ok(key.clone())
+        })
+        .await
+}
+
+// 累计写入超过阈值后在后台检查一次总体积
+fn record_written(app: &AppHandle, len: usize) {
+    let written = WRITTEN_SINCE_CHECK.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+    if written >= EVICT_CHECK_BYTES {
+        WRITTEN_SINCE_CHECK.store(0, Ordering::Relaxed);
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = evict_for_app(&app).await {
+                error!("图片缓存清理失败: {}", e);
+            }
+        });
+    }
+}
+
+async fn download(url: &url::Url) -> Result<Vec<u8>, String> {
+    // 每一跳重定向都要通过白名单检查，否则白名单域名的跳转会把请求带到任意地址
+    let client = reqwest::Client::builder()
+        .user_agent("MyTV/1.0")
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("Too many redirects");
+            }
+            match validate_url(attempt.url().as_str()) {
+                Ok(url) if url == *attempt.url() => attempt.follow(),
+                _ => attempt.stop(),
+            }
+        }))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut resp = client.get(url.as_str()).send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Image request failed: {}", resp.status()));
+    }
+    if resp.content_length().is_some_and(|len| len as usize > MAX_IMAGE_BYTES) {
+        return Err("Image too large".to_string());
+    }
+
+    // 没有 Content-Length 时边读边检查
+    let mut bytes = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| e.to_string())? {
+        if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err("Image too large".to_string());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if sniff_content_type(&bytes).is_none() {
+        return Err("Response is not a supported image".to_string());
+    }
+    Ok(bytes)
+}
+
+// 先写临时文件再改名，协议读取时不会读到写了一半的文件
+fn write_atomic(dir: &Path, key: &str, bytes: &[u8]) -> Result<(), String> {
+    let tmp = dir.join(format!("{}.tmp", key));
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, dir.join(key)).map_err(|e| e.to_string())
+}
+
+// 修改时间作为最近访问时间，淘汰时按它排序
+fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// 按设置中的上限淘汰最久未访问的图片
+pub async fn evict_for_app(app: &AppHandle) -> Result<ImageEvictReport, String> {
+    let conf = get_app_config(app.clone()).await?;
+    let max_bytes = conf.image_cache_max_mb as u64 * 1024 * 1024;
+    let dir = cache_dir(app)?;
+    tauri::async_runtime::spawn_blocking(move || evict(&dir, max_bytes))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+pub fn evict(dir: &Path, max_bytes: u64) -> Result<ImageEvictReport, String> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        files.push((entry.path(), meta.len(), modified));
+    }
+
+    let mut report = ImageEvictReport {
+        files_before: files.len() as u64,
+        bytes_before: files.iter().map(|(_, len, _)| len).sum(),
+        ..Default::default()
+    };
+    if report.bytes_before <= max_bytes {
+        return Ok(report);
+    }
+
+    let target = (max_bytes as f64 * EVICT_TARGET_RATIO) as u64;
+    let mut total = report.bytes_before;
+    files.sort_by_key(|(_, _, modified)| *modified);
+    for (path, len, _) in files {
+        if total <= target {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+            report.files_removed += 1;
+            report.bytes_removed += len;
+        }
+    }
+
+    prune_index(dir);
+    info!(
+        "Image cache evicted {} files ({} bytes)",
+        report.files_removed, report.bytes_removed
+    );
+    Ok(report)
+}
+
+// 删除指向已淘汰原图的索引，下次访问这些地址时重新下载
+fn prune_index(dir: &Path) {
+    let Ok(entries) = fs::read_dir(index_dir(dir)) else { return };
+    for entry in entries.flatten() {
+        let target = fs::read_to_string(entry.path()).unwrap_or_default();
+        let target = target.trim();
+        if target.is_empty() || !dir.join(target).exists() {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// mytv-img 协议：路径为文件 key (原图的内容哈希或某个尺寸)，只读取缓存目录中已有的图片
+pub fn handle_request(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let key = request.uri().path().trim_start_matches('/');
+    if !is_file_key(key) {
+        return status_response(StatusCode::BAD_REQUEST);
+    }
+
+    let bytes = match cache_dir(app).and_then(|dir| fs::read(dir.join(key)).map_err(|e| e.to_string())) {
+        Ok(bytes) => bytes,
+        Err(_) => return status_response(StatusCode::NOT_FOUND),
+    };
+    let content_type = match sniff_content_type(&bytes) {
+        Some(content_type) => content_type,
+        None => return status_response(StatusCode::NOT_FOUND),
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        // key 由内容决定，同一个 key 的内容不会变化
+        .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+        .body(bytes)
+        .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+fn status_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = &[0x89, b'P', b'N', b'G', 0, 1, 2, 3];
+
+    // 每个测试使用独立的临时目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mytv-image-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn url(raw: &str) -> url::Url {
+        validate_url(raw).unwrap()
+    }
+
+    #[test]
+    fn identical_images_from_different_urls_are_stored_once() {
+        let dir = temp_dir("dedupe");
+        let a = url_key(&url("https://image.tmdb.org/t/p/w500/a.png"));
+        let b = url_key(&url("https://walter.trakt.tv/images/b.png"));
+
+        let (key_a, written_a) = store(&dir, &a, PNG).unwrap();
+        let (key_b, written_b) = store(&dir, &b, PNG).unwrap();
+
+        assert_eq!(key_a, key_b);
+        assert!(written_a && !written_b);
+        assert_eq!(lookup(&dir, &a), Some(key_a.clone()));
+        assert_eq!(lookup(&dir, &b), Some(key_a));
+        let files = fs::read_dir(&dir).unwrap().flatten().filter(|e| e.path().is_file()).count();
+        assert_eq!(files, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn eviction_drops_index_entries_of_removed_images() {
+        let dir = temp_dir("evict");
+        let a = url_key(&url("https://image.tmdb.org/t/p/w500/a.png"));
+        let (key, _) = store(&dir, &a, PNG).unwrap();
+        assert!(is_file_key(&key));
+
+        let report = evict(&dir, 0).unwrap();
+
+        assert_eq!(report.files_removed, 1);
+        assert_eq!(lookup(&dir, &a), None);
+        assert!(!index_dir(&dir).join(&a).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
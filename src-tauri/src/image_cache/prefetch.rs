@@ -0,0 +1,159 @@
+use super::ImageVariant;
+use crate::settings::get_app_config;
+use lazy_static::lazy_static;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+const PREFETCH_CONCURRENCY: usize = 4;
+
+// 列表命令自动预取时使用的 group
+pub const MOVIE_TRENDING_GROUP: &str = "movie_trending";
+pub const UP_NEXT_GROUP: &str = "up_next";
+pub const AUTO_GROUPS: [&str; 2] = [MOVIE_TRENDING_GROUP, UP_NEXT_GROUP];
+
+/// 可见的条目先下载，其余按入队顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchPriority {
+    Visible,
+    Upcoming,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchJob {
+    pub url: String,
+    pub variant: ImageVariant,
+    pub priority: PrefetchPriority,
+}
+
+// group 为发起预取的列表，离开页面时按 group 取消
+struct QueuedJob {
+    group: String,
+    url: String,
+    variant: ImageVariant,
+}
+
+impl QueuedJob {
+    fn same_image(&self, url: &str, variant: ImageVariant) -> bool {
+        self.url == url && self.variant == variant
+    }
+}
+
+#[derive(Default)]
+struct PrefetchQueue {
+    visible: VecDeque<QueuedJob>,
+    upcoming: VecDeque<QueuedJob>,
+    workers: usize,
+}
+
+impl PrefetchQueue {
+    fn pop(&mut self) -> Option<QueuedJob> {
+        self.visible.pop_front().or_else(|| self.upcoming.pop_front())
+    }
+}
+
+lazy_static! {
+    static ref QUEUE: Mutex<PrefetchQueue> = Mutex::new(PrefetchQueue::default());
+}
+
+/// 加入预取队列，已在队列中的图片不重复加入 (可见的会提前)
+pub fn enqueue(app: &AppHandle, group: &str, jobs: Vec<PrefetchJob>) {
+    let spawn = {
+        let mut queue = QUEUE.lock().unwrap();
+        for job in jobs {
+            if queue.visible.iter().any(|q| q.same_image(&job.url, job.variant)) {
+                continue;
+            }
+            if let Some(pos) = queue.upcoming.iter().position(|q| q.same_image(&job.url, job.variant)) {
+                if job.priority == PrefetchPriority::Upcoming {
+                    continue;
+                }
+                queue.upcoming.remove(pos);
+            }
+
+            let queued = QueuedJob {
+                group: group.to_string(),
+                url: job.url,
+                variant: job.variant,
+            };
+            match job.priority {
+                PrefetchPriority::Visible => queue.visible.push_back(queued),
+                PrefetchPriority::Upcoming => queue.upcoming.push_back(queued),
+            }
+        }
+
+        let pending = queue.visible.len() + queue.upcoming.len();
+        let spawn = PREFETCH_CONCURRENCY.saturating_sub(queue.workers).min(pending);
+        queue.workers += spawn;
+        spawn
+    };
+
+    for _ in 0..spawn {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { run_worker(app).await });
+    }
+}
+
+/// 取消某个列表还在排队的预取，已经开始下载的会继续完成，返回移除的数量
+pub fn cancel(group: &str) -> usize {
+    let mut queue = QUEUE.lock().unwrap();
+    let before = queue.visible.len() + queue.upcoming.len();
+    queue.visible.retain(|job| job.group != group);
+    queue.upcoming.retain(|job| job.group != group);
+    let removed = before - queue.visible.len() - queue.upcoming.len();
+    if removed > 0 {
+        info!("Image prefetch for {} cancelled, {} jobs removed", group, removed);
+    }
+    removed
+}
+
+async fn run_worker(app: AppHandle) {
+    loop {
+        let job = {
+            let mut queue = QUEUE.lock().unwrap();
+            match queue.pop() {
+                Some(job) => job,
+                None => {
+                    queue.workers -= 1;
+                    return;
+                }
+            }
+        };
+
+        if let Err(e) = super::get_or_render(&app, &job.url, job.variant).await {
+            debug!("Image prefetch failed for {}: {}", job.url, e);
+        }
+    }
+}
+
+/// 列表命令返回后调用：设置中开启了预取时，按设置的尺寸预取海报，前几项视为可见
+pub fn prefetch_posters(app: &AppHandle, group: &str, urls: Vec<String>) {
+    if urls.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    let group = group.to_string();
+    tauri::async_runtime::spawn(async move {
+        let conf = match get_app_config(app.clone()).await {
+            Ok(conf) if conf.image_prefetch_enabled => conf,
+            _ => return,
+        };
+        let jobs = urls
+            .into_iter()
+            .enumerate()
+            .map(|(i, url)| PrefetchJob {
+                url,
+                variant: conf.image_prefetch_variant,
+                priority: if i < conf.image_prefetch_visible as usize {
+                    PrefetchPriority::Visible
+                } else {
+                    PrefetchPriority::Upcoming
+                },
+            })
+            .collect();
+        enqueue(&app, &group, jobs);
+    });
+}
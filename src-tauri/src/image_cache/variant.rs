@@ -0,0 +1,153 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ImageReader, Limits};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+const JPEG_QUALITY: u8 = 82;
+// 解码前限制尺寸和内存，避免异常图片在解码时占用大量内存
+const MAX_DECODE_DIMENSION: u32 = 10_000;
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// 前端可以请求的图片尺寸，生成后和原图一起缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageVariant {
+    Original,
+    PosterSmall,
+    PosterMedium,
+    FanartMedium,
+    // 模糊处理后的背景图
+    FanartBlur,
+    Thumb,
+}
+
+impl ImageVariant {
+    pub const ALL: [ImageVariant; 6] = [
+        ImageVariant::Original,
+        ImageVariant::PosterSmall,
+        ImageVariant::PosterMedium,
+        ImageVariant::FanartMedium,
+        ImageVariant::FanartBlur,
+        ImageVariant::Thumb,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageVariant::Original => "original",
+            ImageVariant::PosterSmall => "poster_small",
+            ImageVariant::PosterMedium => "poster_medium",
+            ImageVariant::FanartMedium => "fanart_medium",
+            ImageVariant::FanartBlur => "fanart_blur",
+            ImageVariant::Thumb => "thumb",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|v| v.name() == name)
+    }
+
+    // 最大宽度，原图更小时不放大
+    fn max_width(&self) -> Option<u32> {
+        match self {
+            ImageVariant::Original => None,
+            ImageVariant::PosterSmall => Some(185),
+            ImageVariant::PosterMedium => Some(342),
+            ImageVariant::FanartMedium => Some(780),
+            ImageVariant::FanartBlur => Some(480),
+            ImageVariant::Thumb => Some(300),
+        }
+    }
+
+    fn blur_sigma(&self) -> Option<f32> {
+        match self {
+            ImageVariant::FanartBlur => Some(12.0),
+            _ => None,
+        }
+    }
+
+    /// 缓存文件名：原图为内容的哈希，其它尺寸加上后缀
+    pub fn file_key(&self, original_key: &str) -> String {
+        match self {
+            ImageVariant::Original => original_key.to_string(),
+            _ => format!("{}_{}", original_key, self.name()),
+        }
+    }
+}
+
+/// 按尺寸缩放 (可能再模糊)，统一输出 JPEG
+pub fn render(original: &[u8], variant: ImageVariant) -> Result<Vec<u8>, String> {
+    let mut img = decode(original)?;
+
+    if let Some(max_width) = variant.max_width() {
+        if img.width() > max_width {
+            let height = ((img.height() as u64 * max_width as u64) / img.width() as u64).max(1) as u32;
+            img = img.resize_exact(max_width, height, FilterType::CatmullRom);
+        }
+    }
+    if let Some(sigma) = variant.blur_sigma() {
+        img = img.blur(sigma);
+    }
+
+    // JPEG 不支持透明通道
+    let rgb = img.to_rgb8();
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+        .encode_image(&rgb)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+fn decode(bytes: &[u8]) -> Result<image::DynamicImage, String> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    reader.limits(limits);
+    reader.decode().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        RgbaImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for variant in ImageVariant::ALL {
+            assert_eq!(ImageVariant::from_name(variant.name()), Some(variant));
+        }
+        assert_eq!(ImageVariant::from_name("huge"), None);
+    }
+
+    #[test]
+    fn render_scales_down_keeping_aspect_ratio() {
+        let out = render(&png(1000, 1500), ImageVariant::PosterSmall).unwrap();
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!((img.width(), img.height()), (185, 277));
+    }
+
+    #[test]
+    fn render_never_upscales() {
+        let out = render(&png(100, 150), ImageVariant::PosterMedium).unwrap();
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!((img.width(), img.height()), (100, 150));
+    }
+
+    #[test]
+    fn render_rejects_images_over_the_decode_limit() {
+        assert!(render(&png(MAX_DECODE_DIMENSION + 1, 1), ImageVariant::Thumb).is_err());
+    }
+}
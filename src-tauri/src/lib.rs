@@ -1,5 +1,6 @@
 mod db;
 mod app_conf;
+mod image_cache;
 mod model;
 mod settings;
 mod token;
@@ -41,6 +42,12 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_sql::Builder::default().build())
+        .register_asynchronous_uri_scheme_protocol(image_cache::IMAGE_SCHEME, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(image_cache::handle_request(&app, &request));
+            });
+        })
         .invoke_handler(tauri::generate_handler![
             trakt_api::auth::start_trakt_user_auth,
             trakt_api::auth::get_token,
@@ -50,18 +57,42 @@ pub fn run() {
             trakt_api::recommand::shows_recommand,
             trakt_api::recommand::movies_recommand_page,
             trakt_api::recommand::shows_recommand_page,
+            trakt_api::recommand::dismiss_recommendation,
+            trakt_api::hidden::get_hidden_items,
+            trakt_api::hidden::add_hidden_item,
+            trakt_api::hidden::remove_hidden_item,
             trakt_api::search::search_media,
+            trakt_api::search::search_media_advanced,
+            trakt_api::search::search_by_id,
             trakt_api::user::get_user_profile,
             trakt_api::user::get_user_stats,
             trakt_api::user::get_watched,
             trakt_api::user::get_collection,
             trakt_api::user::get_watchlist,
             trakt_api::user::get_history,
+            trakt_api::notes::get_notes,
+            trakt_api::notes::add_note,
+            trakt_api::notes::update_note,
+            trakt_api::notes::delete_note,
+            trakt_api::notes::search_notes,
+            trakt_api::notes::get_item_notes,
+            trakt_api::social::get_public_profile,
+            trakt_api::social::get_followers,
+            trakt_api::social::get_following,
+            trakt_api::social::get_friends,
+            trakt_api::social::get_follow_requests,
+            trakt_api::social::follow_user,
+            trakt_api::social::unfollow_user,
+            trakt_api::social::approve_follow_request,
+            trakt_api::social::deny_follow_request,
+            trakt_api::social::get_friends_activity,
             trakt_api::sync::add_to_collection,
             trakt_api::sync::remove_from_collection,
             trakt_api::sync::add_to_watchlist,
             trakt_api::sync::remove_from_watchlist,
             trakt_api::sync::mark_as_watched,
+            trakt_api::sync::get_playback_progress,
+            trakt_api::sync::remove_playback,
             trakt_api::movie::movie_trending,
             trakt_api::movie::movie_trending_page,
             trakt_api::movie::movie_popular_page,
@@ -70,6 +101,14 @@ pub fn run() {
             trakt_api::movie::movie_translation,
             trakt_api::movie::movie_watched_period,
             trakt_api::movie::movie_collected_period,
+            trakt_api::movie::movie_played_period,
+            trakt_api::movie::movie_favorited_period,
+            trakt_api::movie::movie_recommended_period,
+            trakt_api::movie::movie_boxoffice,
+            trakt_api::movie::movie_releases,
+            trakt_api::movie::movie_aliases,
+            trakt_api::movie::movie_local_release,
+            trakt_api::movie::movie_local_releases,
             trakt_api::shows::show_trending,
             trakt_api::shows::show_trending_page,
             trakt_api::shows::show_popular_page,
@@ -79,10 +118,14 @@ pub fn run() {
             trakt_api::shows::show_seasons,
             trakt_api::shows::season_trans,
             trakt_api::shows::get_season_episodes,
+            trakt_api::shows::get_season_tree,
             trakt_api::shows::get_episode_details,
             trakt_api::shows::episode_trans,
             trakt_api::shows::show_watched_period,
             trakt_api::shows::show_collected_period,
+            trakt_api::shows::show_played_period,
+            trakt_api::shows::show_favorited_period,
+            trakt_api::shows::show_recommended_period,
 
             trakt_api::translation_cache::get_movie_translation_cached,
             trakt_api::translation_cache::get_show_translation_cached,
@@ -90,16 +133,43 @@ pub fn run() {
             trakt_api::translation_cache::get_episode_translation_cached,
             trakt_api::translation_cache::clear_expired_translations,
             trakt_api::translation_cache::get_translation_cache_stats,
+            trakt_api::translation_cache::prefetch_translations,
+            trakt_api::cache_inspector::get_cache_stats,
+            trakt_api::cache_inspector::list_cache_keys,
+            trakt_api::cache_inspector::purge_cache,
+            trakt_api::cache_inspector::refresh_cache_entry,
+            trakt_api::insights::get_title_insights,
+            trakt_api::meta::get_genres,
+            trakt_api::meta::get_certifications,
+            trakt_api::meta::get_countries,
+            trakt_api::meta::get_languages,
+            trakt_api::meta::get_networks,
             trakt_api::calendars::get_calendar_movies,
             trakt_api::calendars::get_calendar_shows,
             trakt_api::calendars::get_calendar_new_shows,
             trakt_api::calendars::get_calendar_premieres,
             trakt_api::calendars::get_my_calendar_shows,
+            trakt_api::calendars::get_calendar_dvd,
+            trakt_api::calendars::get_my_calendar_movies,
+            trakt_api::calendars::get_my_calendar_dvd,
             trakt_api::progress::get_show_progress,
+            trakt_api::progress::get_show_collection_progress,
+            trakt_api::progress::get_missing_episodes,
             trakt_api::progress::get_up_next,
+            trakt_api::progress::get_continue_watching,
             trakt_api::utils::get_proxied_image,
+            trakt_api::utils::prefetch_images,
+            trakt_api::utils::cancel_image_prefetch,
             settings::get_app_config,
-            settings::update_log_level
+            settings::update_log_level,
+            settings::update_home_country,
+            settings::update_cache_limits,
+            settings::run_cache_maintenance,
+            settings::get_cache_maintenance_report,
+            settings::get_cache_policies,
+            settings::update_cache_ttl_override,
+            settings::update_translation_language,
+            settings::update_image_prefetch
         ])
         .setup(|app| {
             // 初始化后端使用的 DB pool
@@ -168,6 +238,17 @@ pub fn run() {
             let client = ApiClient::new(&app.handle());
             app.manage(Mutex::new(client));
 
+            // 加载设置中的缓存 TTL 覆盖
+            if let Ok(conf) = tauri::async_runtime::block_on(settings::get_app_config(app.handle().clone())) {
+                db::policy::set_overrides(conf.cache_ttl_overrides);
+            }
+
+            // 定期根据 Trakt 的更新列表失效媒体缓存，并清理、压缩缓存
+            if pool.is_some() {
+                trakt_api::updates::spawn_media_updates_job(app.handle().clone());
+                db::maintenance::spawn_cache_maintenance_job(app.handle().clone());
+            }
+
             if let Some(window) = app.get_webview_window("main") {
                 #[cfg(target_os = "macos")]
                 // Use Sidebar for a more "native" vibrant material look
@@ -1,5 +1,9 @@
 pub mod assets;
+pub mod discovery;
+pub mod insights;
 pub mod movie;
+pub mod notes;
+pub mod people;
 pub mod recommand;
 pub mod shows;
 pub mod translation;
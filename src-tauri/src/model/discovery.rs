@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 榜单类接口 (trending / popular / anticipated ...) 共用的过滤条件
+/// 列表字段以逗号拼接，区间字段使用 Trakt 的 `min-max` 写法，如 `2010-2020`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DiscoveryFilter {
+    pub query: Option<String>,
+    pub years: Option<String>,
+    pub genres: Option<Vec<String>>,
+    pub languages: Option<Vec<String>>,
+    pub countries: Option<Vec<String>>,
+    pub runtimes: Option<String>,
+    pub ratings: Option<String>,
+    pub certifications: Option<Vec<String>>,
+    // 以下仅对剧集生效
+    pub networks: Option<Vec<String>>,
+    pub status: Option<Vec<String>>,
+}
+
+impl DiscoveryFilter {
+    /// 合并旧版命令的 genres / countries 参数，显式 filter 优先
+    pub fn with_legacy(
+        filter: Option<DiscoveryFilter>,
+        genres: Option<String>,
+        countries: Option<String>,
+    ) -> Self {
+        let mut filter = filter.unwrap_or_default();
+        if filter.genres.is_none() {
+            filter.genres = split_legacy(genres);
+        }
+        if filter.countries.is_none() {
+            filter.countries = split_legacy(countries);
+        }
+        filter
+    }
+
+    /// 转为请求参数，空值和 "all" 会被忽略
+    pub fn to_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        for (key, value) in self.normalized() {
+            params.insert(key.to_string(), value);
+        }
+        params
+    }
+
+    /// 稳定的缓存 key 片段，条件相同但顺序不同的过滤器得到相同的 key
+    pub fn cache_key(&self) -> String {
+        self.normalized()
+            .into_iter()
+            .map(|(key, value)| format!("_{}-{}", key, value))
+            .collect()
+    }
+
+    fn normalized(&self) -> Vec<(&'static str, String)> {
+        let mut out = Vec::new();
+        let mut push_str = |key: &'static str, value: &Option<String>| {
+            if let Some(v) = value.as_deref().map(str::trim) {
+                if !v.is_empty() && v != "all" {
+                    out.push((key, v.to_string()));
+                }
+            }
+        };
+        push_str("query", &self.query);
+        push_str("years", &self.years);
+        push_str("runtimes", &self.runtimes);
+        push_str("ratings", &self.ratings);
+
+        let lists = [
+            ("genres", &self.genres),
+            ("languages", &self.languages),
+            ("countries", &self.countries),
+            ("certifications", &self.certifications),
+            ("networks", &self.networks),
+            ("status", &self.status),
+        ];
+        for (key, values) in lists {
+            if let Some(values) = values {
+                let mut values: Vec<String> = values
+                    .iter()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty() && v != "all")
+                    .collect();
+                if values.is_empty() {
+                    continue;
+                }
+                values.sort();
+                values.dedup();
+                out.push((key, values.join(",")));
+            }
+        }
+        out.sort_by(|a, b| a.0.cmp(b.0));
+        out
+    }
+}
+
+fn split_legacy(value: Option<String>) -> Option<Vec<String>> {
+    let value = value?;
+    if value.is_empty() || value == "all" {
+        return None;
+    }
+    Some(value.split(',').map(|s| s.to_string()).collect())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Genre {
+    pub name: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Certification {
+    pub name: String,
+    pub slug: String,
+    pub description: Option<String>,
+}
+
+// 按国家分组的分级列表，如 {"us": [...]}
+pub type Certifications = HashMap<String, Vec<Certification>>;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Country {
+    pub name: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Language {
+    pub name: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Network {
+    pub name: String,
+    pub country: Option<String>,
+    pub ids: Option<NetworkIds>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkIds {
+    pub trakt: Option<u32>,
+    pub tmdb: Option<u32>,
+}
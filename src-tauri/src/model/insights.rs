@@ -0,0 +1,42 @@
+use crate::model::user::{RatingDistribution, User};
+use serde::{Deserialize, Serialize};
+
+// /movies/:id/stats, /shows/:id/stats 以及季、集的统计
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TitleStats {
+    #[serde(default)]
+    pub watchers: u32,
+    #[serde(default)]
+    pub plays: u32,
+    #[serde(default)]
+    pub collectors: u32,
+    #[serde(default)]
+    pub comments: u32,
+    #[serde(default)]
+    pub lists: u32,
+    #[serde(default)]
+    pub votes: u32,
+    #[serde(default)]
+    pub favorited: u32,
+    #[serde(default)]
+    pub recommended: u32,
+    // 仅剧集返回
+    pub collected_episodes: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TitleRatings {
+    pub rating: f32,
+    pub votes: u32,
+    pub distribution: RatingDistribution,
+}
+
+/// 详情页统计图表所需的全部数据，某一项获取失败时为空
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TitleInsights {
+    pub media_type: String,
+    pub id: u32,
+    pub stats: Option<TitleStats>,
+    pub ratings: Option<TitleRatings>,
+    pub watching: Vec<User>,
+}
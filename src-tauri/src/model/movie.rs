@@ -80,6 +80,52 @@ pub struct MovieCollected {
     pub movie: Movie,
 }
 
+// played 榜单与 watched 榜单返回结构相同
+pub type MoviePlayed = MovieWatched;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovieFavorited {
+    pub user_count: u32,
+    pub movie: Movie,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovieRecommended {
+    pub user_count: u32,
+    pub movie: Movie,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovieBoxOffice {
+    pub revenue: u64,
+    pub movie: Movie,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovieRelease {
+    pub country: String,
+    pub certification: Option<String>,
+    pub release_date: Option<String>,
+    // unknown / premiere / limited / theatrical / digital / physical / tv
+    pub release_type: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovieAlias {
+    pub title: String,
+    pub country: String,
+}
+
+/// 按本地国家整理后的发行信息，用于详情页和日历
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovieLocalRelease {
+    pub country: String,
+    pub certification: Option<String>,
+    pub title: Option<String>,
+    pub releases: Vec<MovieRelease>,
+}
+
 // 类型别名
 pub type MovieWatchedResponse = Vec<MovieWatched>;
 pub type MovieCollectedResponse = Vec<MovieCollected>;
@@ -0,0 +1,77 @@
+use crate::model::movie::Movie;
+use crate::model::people::Person;
+use crate::model::shows::{Episode, Season, Show};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Note {
+    pub id: u64,
+    pub notes: String,
+    pub privacy: Option<String>,
+    #[serde(default)]
+    pub spoiler: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// 新增笔记的请求，attached_to 为 history / collection / rating 时需同时传入 attached_id
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewNote {
+    pub media_type: String,
+    pub trakt_id: u32,
+    pub notes: String,
+    pub spoiler: Option<bool>,
+    pub privacy: Option<String>,
+    pub attached_to: Option<String>,
+    pub attached_id: Option<u64>,
+}
+
+// 笔记可以挂在观看记录、收藏条目或评分上
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NoteAttachment {
+    #[serde(rename = "type")]
+    pub attachment_type: String,
+    pub id: Option<u64>,
+}
+
+// /users/:id/notes 返回的条目
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NoteItem {
+    pub attached_to: Option<NoteAttachment>,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub movie: Option<Movie>,
+    pub show: Option<Show>,
+    pub season: Option<Season>,
+    pub episode: Option<Episode>,
+    pub person: Option<Person>,
+    pub note: Note,
+}
+
+impl NoteItem {
+    pub fn trakt_id(&self) -> Option<u32> {
+        match self.item_type.as_str() {
+            "movie" => self.movie.as_ref().map(|m| m.ids.trakt),
+            "show" => self.show.as_ref().map(|s| s.ids.trakt),
+            "season" => self.season.as_ref().map(|s| s.ids.trakt),
+            "episode" => self.episode.as_ref().map(|e| e.ids.trakt),
+            "person" => self.person.as_ref().map(|p| p.ids.trakt),
+            _ => None,
+        }
+    }
+}
+
+// 本地镜像中的笔记，item 为原始条目 JSON，便于离线展示
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalNote {
+    pub id: u64,
+    pub media_type: String,
+    pub trakt_id: u32,
+    pub attached_to: Option<String>,
+    pub attached_id: Option<u64>,
+    pub notes: String,
+    pub spoiler: bool,
+    pub privacy: Option<String>,
+    pub item: Option<serde_json::Value>,
+    pub updated_at: i64,
+}
@@ -0,0 +1,24 @@
+use crate::model::assets::Images;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Person {
+    pub name: String,
+    pub ids: PersonIds,
+    pub biography: Option<String>,
+    pub birthday: Option<String>,
+    pub death: Option<String>,
+    pub birthplace: Option<String>,
+    pub homepage: Option<String>,
+    pub known_for_department: Option<String>,
+    #[serde(default)]
+    pub images: Images,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonIds {
+    pub trakt: u32,
+    pub slug: String,
+    pub imdb: Option<String>,
+    pub tmdb: Option<u32>,
+}
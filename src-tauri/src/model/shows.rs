@@ -92,6 +92,21 @@ pub struct ShowCollected {
     pub show: Show,
 }
 
+// played 榜单与 watched 榜单返回结构相同
+pub type ShowPlayed = ShowWatched;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShowFavorited {
+    pub user_count: u32,
+    pub show: Show,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShowRecommended {
+    pub user_count: u32,
+    pub show: Show,
+}
+
 pub type ShowWatchedResponse = Vec<ShowWatched>;
 pub type ShowCollectedResponse = Vec<ShowCollected>;
 
@@ -132,6 +147,39 @@ pub struct SeasonTranslation {
 
 pub type SeasonTranslations = Vec<SeasonTranslation>;
 
+// extended=episodes 返回的季度，带全部剧集
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeasonWithEpisodes {
+    #[serde(flatten)]
+    pub season: Season,
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
+}
+
+/// 季度树：全部季度及剧集，合并了用户的观看进度
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeasonTree {
+    pub show_id: u32,
+    pub updated_at: Option<String>,
+    pub seasons: Vec<SeasonNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeasonNode {
+    #[serde(flatten)]
+    pub season: Season,
+    pub completed: u32,
+    pub episodes: Vec<EpisodeNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EpisodeNode {
+    #[serde(flatten)]
+    pub episode: Episode,
+    pub watched: bool,
+    pub last_watched_at: Option<String>,
+}
+
 // Episode 结构体 (用于 history API)
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Episode {
@@ -161,9 +209,48 @@ pub struct ShowProgress {
     pub aired: u32,
     pub completed: u32,
     pub last_watched_at: Option<String>,
+    // 仅 collection 进度返回
+    #[serde(default)]
+    pub last_collected_at: Option<String>,
     pub reset_at: Option<String>,
     pub next_episode: Option<Episode>,
     pub last_episode: Option<Episode>,
+    #[serde(default)]
+    pub seasons: Vec<SeasonProgress>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeasonProgress {
+    pub number: u32,
+    pub aired: u32,
+    pub completed: u32,
+    #[serde(default)]
+    pub episodes: Vec<EpisodeProgress>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EpisodeProgress {
+    pub number: u32,
+    pub completed: bool,
+    #[serde(default)]
+    pub last_watched_at: Option<String>,
+    #[serde(default)]
+    pub collected_at: Option<String>,
+}
+
+/// 收藏中缺失的已播出剧集
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MissingEpisodesItem {
+    pub show: Show,
+    pub aired: u32,
+    pub completed: u32,
+    pub missing: Vec<MissingEpisode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MissingEpisode {
+    pub season: u32,
+    pub number: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -8,6 +8,23 @@ pub struct TranslationData {
     pub updated_at: u64, // 时间戳
 }
 
+// 批量预取的一项，type 为 movie 或 show
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct TranslationTarget {
+    #[serde(rename = "type")]
+    pub media_type: String,
+    pub id: u32,
+}
+
+// 批量预取的结果，data 为空表示没有可用的翻译
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TranslationBatchItem {
+    #[serde(rename = "type")]
+    pub media_type: String,
+    pub id: u32,
+    pub data: Option<TranslationData>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TranslationCacheItem {
     pub data: Option<TranslationData>,
@@ -10,36 +10,43 @@ pub struct UserProfile {
     pub permissions: Permissions,
 }
 
+// 非 extended=full 的接口 (关注列表等) 不返回 joined_at / images 等字段
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct User {
     pub username: String,
     pub private: bool,
     pub name: String,
     pub vip: bool,
+    #[serde(default)]
     pub vip_ep: bool,
     pub ids: UserIds,
+    #[serde(default)]
     pub joined_at: String,
     pub location: Option<String>,
     pub about: Option<String>,
     pub gender: Option<String>,
     pub age: Option<u32>,
+    #[serde(default)]
     pub images: UserImages,
+    #[serde(default)]
     pub vip_og: bool,
+    #[serde(default)]
     pub vip_years: u32,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct UserIds {
     pub slug: String,
+    #[serde(default)]
     pub uuid: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct UserImages {
     pub avatar: UserAvatar,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct UserAvatar {
     pub full: String,
 }
@@ -201,3 +208,66 @@ pub struct RatingDistribution {
     #[serde(rename = "10")]
     pub ten: u32,
 }
+
+// 用户自建列表 (搜索结果中的 list 类型)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TraktList {
+    pub name: String,
+    pub description: Option<String>,
+    pub privacy: Option<String>,
+    pub display_numbers: Option<bool>,
+    pub allow_comments: Option<bool>,
+    pub sort_by: Option<String>,
+    pub sort_how: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub item_count: Option<u32>,
+    pub comment_count: Option<u32>,
+    pub likes: Option<u32>,
+    pub ids: TraktListIds,
+    pub user: Option<UserSummary>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TraktListIds {
+    pub trakt: u32,
+    pub slug: String,
+}
+
+// 列表所有者、隐藏用户等场景只保证有基础字段，不能直接复用 User
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserSummary {
+    pub username: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub private: bool,
+    pub ids: UserSummaryIds,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserSummaryIds {
+    pub slug: String,
+}
+
+// 关注者 / 正在关注 / 好友列表条目
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Follower {
+    pub followed_at: Option<String>,
+    pub friends_at: Option<String>,
+    pub user: User,
+}
+
+// 待处理的关注请求
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FollowRequest {
+    pub id: u64,
+    pub requested_at: String,
+    pub user: User,
+}
+
+// follow 接口的返回：approved_at 为空表示对方是私密账号，需等待批准
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FollowResult {
+    pub approved_at: Option<String>,
+    pub user: User,
+}
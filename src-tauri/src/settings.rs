@@ -1,6 +1,10 @@
 use crate::app_conf::{get_config, AppConf};
 use tauri::{command, AppHandle, Manager};
 use crate::db::{DbPool, cache};
+use crate::db::maintenance::{self, MaintenanceReport};
+use crate::db::policy::{self, CacheStore, TtlOverride};
+use crate::image_cache::{self, ImageVariant};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::path::Path;
@@ -53,3 +57,215 @@ pub async fn update_log_level(app: AppHandle, level: String) -> Result<(), Strin
     
     Ok(())
 }
+
+#[command]
+pub async fn update_home_country(app: AppHandle, country: String) -> Result<(), String> {
+    let country = normalize_country(&country).ok_or_else(|| format!("Invalid country code: {}", country))?;
+
+    let mut config = get_app_config(app.clone()).await?;
+    config.home_country = country;
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+         cache::set_config(&pool.0, "app_conf", &serde_json::to_value(&config).unwrap())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[command]
+pub async fn update_cache_limits(
+    app: AppHandle,
+    max_rows: u32,
+    max_db_mb: u32,
+    image_max_mb: Option<u32>,
+) -> Result<(), String> {
+    if max_rows == 0 || max_db_mb == 0 || image_max_mb == Some(0) {
+        return Err("Cache limits must be greater than 0".to_string());
+    }
+
+    let mut config = get_app_config(app.clone()).await?;
+    config.cache_max_rows = max_rows;
+    config.cache_max_db_mb = max_db_mb;
+    if let Some(image_max_mb) = image_max_mb {
+        config.image_cache_max_mb = image_max_mb;
+    }
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+         cache::set_config(&pool.0, "app_conf", &serde_json::to_value(&config).unwrap())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// 立即执行一次缓存维护
+#[command]
+pub async fn run_cache_maintenance(app: AppHandle) -> Result<MaintenanceReport, String> {
+    maintenance::run_for_app(&app).await
+}
+
+/// 最近一次缓存维护的结果，从未执行过时返回 None
+#[command]
+pub async fn get_cache_maintenance_report(app: AppHandle) -> Result<Option<MaintenanceReport>, String> {
+    match app.try_state::<DbPool>() {
+        Some(pool) => Ok(maintenance::last_report(&pool.0).await),
+        None => Err("Database not initialized".to_string()),
+    }
+}
+
+/// 缓存策略当前生效的 TTL 和默认值
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachePolicyInfo {
+    pub name: String,
+    pub store: CacheStore,
+    pub event: String,
+    pub ttl_ms: i64,
+    pub stale_ms: i64,
+    pub default_ttl_ms: i64,
+    pub default_stale_ms: i64,
+}
+
+#[command]
+pub async fn get_cache_policies() -> Result<Vec<CachePolicyInfo>, String> {
+    Ok(policy::POLICIES
+        .iter()
+        .map(|p| {
+            let (ttl_ms, stale_ms) = p.effective();
+            CachePolicyInfo {
+                name: p.name.to_string(),
+                store: p.store,
+                event: p.event.to_string(),
+                ttl_ms,
+                stale_ms,
+                default_ttl_ms: p.ttl_ms,
+                default_stale_ms: p.stale_ms,
+            }
+        })
+        .collect())
+}
+
+/// 覆盖某个缓存策略的 TTL / 陈旧时间，两者都为空时恢复默认
+#[command]
+pub async fn update_cache_ttl_override(
+    app: AppHandle,
+    name: String,
+    ttl_ms: Option<i64>,
+    stale_ms: Option<i64>,
+) -> Result<(), String> {
+    if policy::find_policy(&name).is_none() {
+        return Err(format!("Unknown cache policy: {}", name));
+    }
+    if ttl_ms.is_some_and(|v| v <= 0) || stale_ms.is_some_and(|v| v < 0) {
+        return Err("Invalid cache duration".to_string());
+    }
+
+    let mut config = get_app_config(app.clone()).await?;
+    if ttl_ms.is_none() && stale_ms.is_none() {
+        config.cache_ttl_overrides.remove(&name);
+    } else {
+        config.cache_ttl_overrides.insert(name, TtlOverride { ttl_ms, stale_ms });
+    }
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+         cache::set_config(&pool.0, "app_conf", &serde_json::to_value(&config).unwrap())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    policy::set_overrides(config.cache_ttl_overrides);
+
+    Ok(())
+}
+
+/// 开启或关闭列表的图片预取，可同时设置预取的尺寸和优先下载的条目数
+#[command]
+pub async fn update_image_prefetch(
+    app: AppHandle,
+    enabled: bool,
+    variant: Option<ImageVariant>,
+    visible_count: Option<u32>,
+) -> Result<(), String> {
+    let mut config = get_app_config(app.clone()).await?;
+    config.image_prefetch_enabled = enabled;
+    if let Some(variant) = variant {
+        config.image_prefetch_variant = variant;
+    }
+    if let Some(visible_count) = visible_count {
+        config.image_prefetch_visible = visible_count;
+    }
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+         cache::set_config(&pool.0, "app_conf", &serde_json::to_value(&config).unwrap())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    // 关闭时清空自动预取的队列
+    if !enabled {
+        for group in image_cache::prefetch::AUTO_GROUPS {
+            image_cache::prefetch::cancel(group);
+        }
+    }
+
+    Ok(())
+}
+
+/// 设置翻译语言和回退顺序，语言格式为 zh 或 zh-TW，回退中可以使用 original 表示原文
+#[command]
+pub async fn update_translation_language(
+    app: AppHandle,
+    language: String,
+    fallbacks: Option<Vec<String>>,
+) -> Result<(), String> {
+    let language = normalize_language_tag(&language)
+        .filter(|l| l != "original")
+        .ok_or_else(|| format!("Invalid language: {}", language))?;
+
+    let mut config = get_app_config(app.clone()).await?;
+    if let Some(fallbacks) = fallbacks {
+        config.translation_fallbacks = fallbacks
+            .iter()
+            .map(|f| normalize_language_tag(f).ok_or_else(|| format!("Invalid language: {}", f)))
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+    config.translation_language = language;
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+         cache::set_config(&pool.0, "app_conf", &serde_json::to_value(&config).unwrap())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// 国家代码统一为小写的两位字母 (ISO 3166-1 alpha-2)，格式不对时返回 None
+pub(crate) fn normalize_country(country: &str) -> Option<String> {
+    let country = country.trim().to_lowercase();
+    if country.len() == 2 && country.chars().all(|c| c.is_ascii_lowercase()) {
+        Some(country)
+    } else {
+        None
+    }
+}
+
+// 统一为 语言小写-地区大写，如 zh-tw -> zh-TW
+fn normalize_language_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().replace('_', "-");
+    if tag.eq_ignore_ascii_case("original") {
+        return Some("original".to_string());
+    }
+    let mut parts = tag.split('-');
+    let language = parts.next()?.to_lowercase();
+    let region = parts.next().map(|r| r.to_uppercase());
+    if parts.next().is_some() || language.len() != 2 || !language.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+    match region {
+        Some(r) if r.len() == 2 && r.chars().all(|c| c.is_ascii_uppercase()) => Some(format!("{}-{}", language, r)),
+        Some(_) => None,
+        None => Some(language),
+    }
+}
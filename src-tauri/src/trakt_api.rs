@@ -1,18 +1,29 @@
 pub mod auth;
+pub mod cached_fetch;
+pub mod cache_inspector;
 pub mod calendars;
+pub mod hidden;
+pub mod insights;
+pub mod meta;
 pub mod movie;
+pub mod notes;
 pub mod progress;
 pub mod recommand;
 pub mod search;
 pub mod shows;
+pub mod single_flight;
+pub mod social;
 pub mod sync;
 pub mod translation_cache;
+pub mod updates;
 pub mod user;
 pub mod utils;
 
 use crate::app_conf::get_config;
+use crate::db::{cache, DbPool};
 use crate::token::Token;
 use auth::refresh_token;
+use single_flight::SingleFlight;
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
 use reqwest::header::{HeaderMap, HeaderValue};
@@ -34,6 +45,13 @@ const API_MAP: &str = include_str!("../api.json");
 // 使用 lazy_static 创建全局静态配置实例
 lazy_static! {
     pub static ref API: Api = Api::new();
+    // 正在进行中的 GET 请求，key 为完整 URL
+    static ref HTTP_FLIGHTS: SingleFlight<Result<Value, u16>> = SingleFlight::new();
+}
+
+/// 复制一份全局 client，请求期间不占用全局锁，相同的并发 GET 才能在 HTTP_FLIGHTS 中合并
+pub(crate) async fn api_client(app: &AppHandle) -> ApiClient {
+    app.state::<Mutex<ApiClient>>().lock().await.clone()
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -55,6 +73,11 @@ pub struct Api {
     pub shows: ShowApi,
     pub sync: SyncApi,
     pub calendars: CalendarsApi,
+    pub meta: MetaApi,
+    pub insights: InsightsApi,
+    pub hidden: HiddenApi,
+    pub notes: NotesApi,
+    pub social: SocialApi,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -69,11 +92,15 @@ pub struct AuthApi {
 pub struct RecommandApi {
     pub movies: Entry,
     pub shows: Entry,
+    pub dismiss_movie: Entry,
+    pub dismiss_show: Entry,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SearchApi {
     pub text: Entry,
+    pub typed: Entry,
+    pub id_lookup: Entry,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -95,6 +122,13 @@ pub struct MovieApi {
     pub trans: Entry,
     pub watched: Entry,
     pub collected: Entry,
+    pub played: Entry,
+    pub favorited: Entry,
+    pub recommended: Entry,
+    pub boxoffice: Entry,
+    pub releases: Entry,
+    pub aliases: Entry,
+    pub updates: Entry,
 }
 
 
@@ -108,13 +142,47 @@ pub struct ShowApi {
     pub seasons: Entry,
     pub season_trans: Entry,
     pub season_episodes: Entry,
+    pub season_tree: Entry,
     pub episode_details: Entry,
     pub episode_trans: Entry,
     pub progress: Entry,
+    pub collection_progress: Entry,
     pub watched: Entry,
     pub collected: Entry,
+    pub played: Entry,
+    pub favorited: Entry,
+    pub recommended: Entry,
+    pub updates: Entry,
+}
+
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HiddenApi {
+    pub items: Entry,
+    pub add: Entry,
+    pub remove: Entry,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotesApi {
+    pub list: Entry,
+    pub add: Entry,
+    pub update: Entry,
+    pub delete: Entry,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SocialApi {
+    pub profile: Entry,
+    pub followers: Entry,
+    pub following: Entry,
+    pub friends: Entry,
+    pub follow: Entry,
+    pub unfollow: Entry,
+    pub requests: Entry,
+    pub approve_request: Entry,
+    pub deny_request: Entry,
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SyncApi {
@@ -123,6 +191,8 @@ pub struct SyncApi {
     pub add_to_watchlist: Entry,
     pub remove_from_watchlist: Entry,
     pub add_to_history: Entry,
+    pub playback: Entry,
+    pub remove_playback: Entry,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -133,6 +203,24 @@ pub struct CalendarsApi {
     pub new_shows: Entry,
     pub season_premieres: Entry,
     pub dvd: Entry,
+    pub my_movies: Entry,
+    pub my_dvd: Entry,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct InsightsApi {
+    pub stats: Entry,
+    pub ratings: Entry,
+    pub watching: Entry,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetaApi {
+    pub genres: Entry,
+    pub certifications: Entry,
+    pub countries: Entry,
+    pub languages: Entry,
+    pub networks: Entry,
 }
 
 impl Api {
@@ -141,6 +229,46 @@ impl Api {
     }
 }
 
+/// 榜单类请求的通用实现：先查 api_response_cache，未命中再请求并写入缓存
+pub(crate) async fn cached_chart_request<T>(
+    app: &AppHandle,
+    entry: &Entry,
+    uri: String,
+    cache_key: &str,
+    params: HashMap<String, String>,
+    limit: Option<u32>,
+    page: Option<u32>,
+) -> Result<Vec<T>, u16>
+where
+    T: serde::de::DeserializeOwned,
+{
+    if let Some(pool) = app.try_state::<DbPool>() {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, cache_key).await {
+            if let Ok(data) = serde_json::from_value::<Vec<T>>(json) {
+                return Ok(data);
+            }
+        }
+    }
+
+    let mut client = api_client(app).await;
+    let result = client
+        .req_api(app, entry.method.as_str(), uri, Some(params), None, limit, page, true)
+        .await?;
+
+    match serde_json::from_value::<Vec<T>>(result.clone()) {
+        Ok(data) => {
+            if let Some(pool) = app.try_state::<DbPool>() {
+                cache::set_api_response_cache(&pool.0, cache_key, &result).await;
+            }
+            Ok(data)
+        }
+        Err(e) => {
+            error!("解析 {} 失败: {:?}", cache_key, e);
+            Err(500)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     authenticated: bool,
@@ -246,12 +374,31 @@ impl ApiClient {
             final_params.insert("page".to_string(), page.to_string());
         }
 
-        // 将参数添加到URL
-        for (key, value) in &final_params {
+        // 将参数添加到URL，按 key 排序保证相同参数得到相同的 URL
+        let mut sorted_params: Vec<_> = final_params.iter().collect();
+        sorted_params.sort();
+        for (key, value) in sorted_params {
             url.query_pairs_mut()
                 .append_pair(key.as_str(), value.as_str());
         }
 
+        // 并发的相同 GET 请求只发一次
+        if method.eq_ignore_ascii_case("get") && body.is_none() {
+            let flight_key = format!("GET {} {}", url.as_str(), self.authenticated);
+            return HTTP_FLIGHTS
+                .run(flight_key, || self.send_request(app, method, url, None))
+                .await;
+        }
+        self.send_request(app, method, url, body).await
+    }
+
+    async fn send_request(
+        &mut self,
+        app: &AppHandle,
+        method: &str,
+        url: Url,
+        body: Option<Value>,
+    ) -> Result<Value, u16> {
         debug!("=== API 请求详情 ===");
         debug!("URL: {}", url.as_str());
         debug!("Method: {}", method);
@@ -336,6 +483,8 @@ impl ApiClient {
                 if resp.status().is_success() {
                     let body = resp.text().await;
                     match body {
+                        // DELETE 等接口返回 204，响应体为空
+                        Ok(body) if body.trim().is_empty() => Ok(Value::Null),
                         Ok(body) => Ok(serde_json::from_str(&body).unwrap()),
                         Err(e) => {
                             error!("解析响应体失败: {:?}", e);
@@ -349,7 +498,11 @@ impl ApiClient {
                         let result = refresh_token(app).await;
                         if let Ok(token) = result {
                             info!("刷新token成功: {:?}", &token);
-                            self.refresh_client(Some(token));
+                            self.refresh_client(Some(token.clone()));
+                            // 请求使用的是副本，全局 client 也要更新
+                            if let Some(client_state) = app.try_state::<Mutex<ApiClient>>() {
+                                client_state.lock().await.refresh_client(Some(token));
+                            }
                             // Token 刷新成功，但不自动重试请求
                             // 让调用者处理重试逻辑
                         } else {
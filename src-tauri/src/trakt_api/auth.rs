@@ -102,7 +102,7 @@ pub async fn get_token(app: AppHandle, code: &str) -> Result<Token, String> {
     };
 
     let resp_body: Result<Value, u16> = {
-        let mut client = client.lock().await;
+        let mut client = client.lock().await.clone();
         client
             .req_api(
                 &app,
@@ -0,0 +1,84 @@
+use crate::db::inspector::{self, CacheKeyInfo, CacheTableStats, CACHE_TABLES};
+use crate::db::DbPool;
+use log::info;
+use serde_json::Value;
+use tauri::{command, AppHandle, Manager};
+
+const DEFAULT_KEY_LIMIT: u32 = 200;
+
+/// 各缓存表的行数、体积、过期行数、命中率以及最旧/最新的条目
+#[command]
+pub async fn get_cache_stats(app: AppHandle) -> Result<Vec<CacheTableStats>, String> {
+    let pool = db_pool(&app)?;
+    let mut stats = Vec::new();
+    for table in CACHE_TABLES.iter() {
+        stats.push(inspector::table_stats(&pool, table).await.map_err(|e| e.to_string())?);
+    }
+    Ok(stats)
+}
+
+#[command]
+pub async fn list_cache_keys(
+    app: AppHandle,
+    table: String,
+    prefix: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<CacheKeyInfo>, String> {
+    let pool = db_pool(&app)?;
+    let table = inspector::find_table(&table).ok_or_else(|| format!("Unknown cache table: {}", table))?;
+    inspector::list_keys(&pool, table, prefix.as_deref().unwrap_or(""), limit.unwrap_or(DEFAULT_KEY_LIMIT))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 删除缓存：按 key 前缀删除任意表，media_cache 还可以按 media_type / trakt_id 删除
+/// 至少需要一个条件，prefix 传空字符串表示清空整张表
+#[command]
+pub async fn purge_cache(
+    app: AppHandle,
+    table: String,
+    prefix: Option<String>,
+    media_type: Option<String>,
+    trakt_id: Option<u32>,
+) -> Result<u64, String> {
+    let pool = db_pool(&app)?;
+    let cache_table = inspector::find_table(&table).ok_or_else(|| format!("Unknown cache table: {}", table))?;
+
+    let removed = if media_type.is_some() || trakt_id.is_some() {
+        if cache_table.name != "media_cache" {
+            return Err("media_type / trakt_id only apply to media_cache".to_string());
+        }
+        inspector::purge_media(&pool, media_type.as_deref(), trakt_id).await
+    } else if let Some(prefix) = prefix {
+        inspector::purge_prefix(&pool, cache_table, &prefix).await
+    } else {
+        return Err("No purge condition given".to_string());
+    }
+    .map_err(|e| e.to_string())?;
+
+    info!("Purged {} rows from {}", removed, table);
+    Ok(removed)
+}
+
+/// 删除单个媒体条目的缓存并立即重新拉取，返回最新数据
+#[command]
+pub async fn refresh_cache_entry(app: AppHandle, media_type: String, id: u32) -> Result<Value, u16> {
+    let pool = db_pool(&app).map_err(|_| 500u16)?;
+    inspector::purge_media(&pool, Some(&media_type), Some(id)).await.map_err(|_| 500u16)?;
+
+    let value = match media_type.as_str() {
+        "movie" => serde_json::to_value(super::movie::movie_details(app.clone(), id).await?),
+        "show" => serde_json::to_value(super::shows::show_details(app.clone(), id).await?),
+        "show_seasons" => serde_json::to_value(super::shows::show_seasons(app.clone(), id).await?),
+        "show_season_tree" => serde_json::to_value(super::shows::get_season_tree(app.clone(), id).await?),
+        _ => return Err(400),
+    };
+    info!("Cache entry {}_{} refreshed", media_type, id);
+    value.map_err(|_| 500)
+}
+
+fn db_pool(app: &AppHandle) -> Result<sqlx::SqlitePool, String> {
+    app.try_state::<DbPool>()
+        .map(|pool| pool.0.clone())
+        .ok_or_else(|| "Database not initialized".to_string())
+}
@@ -0,0 +1,151 @@
+use crate::db::cache::{self, CacheKey};
+use crate::db::policy::{CachePolicy, Freshness};
+use crate::db::DbPool;
+use crate::trakt_api::single_flight::{InFlightGuard, InFlightSet, SingleFlight};
+use lazy_static::lazy_static;
+use log::{debug, error, info};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use tauri::{AppHandle, Emitter, Manager};
+
+lazy_static! {
+    // 缓存未命中时同一个 key 只请求一次
+    static ref FETCH_FLIGHTS: SingleFlight<Result<serde_json::Value, u16>> = SingleFlight::new();
+    // 同一个 key 同时只允许一个后台刷新
+    pub(crate) static ref REFRESHING: InFlightSet = InFlightSet::new();
+}
+
+/// 按缓存策略读取：新鲜直接返回；陈旧先返回旧数据，后台刷新后发出策略中的更新事件；
+/// 过期或未命中时同步请求并写入缓存
+pub(crate) async fn cached_fetch<T, F, Fut>(
+    app: &AppHandle,
+    policy: &'static CachePolicy,
+    key: CacheKey,
+    fetch: F,
+) -> Result<T, u16>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    F: FnOnce(AppHandle) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, u16>> + Send + 'static,
+{
+    let counter = cache::counter_for(policy.store);
+    let mut cached = None;
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+        if let Some(entry) = cache::read_cache_entry(&pool.0, policy.store, &key).await {
+            let freshness = policy.freshness(entry.updated_at, entry.expires_at, cache::get_timestamp());
+            if freshness != Freshness::Expired {
+                if let Ok(data) = serde_json::from_value::<T>(entry.data) {
+                    cached = Some((data, freshness));
+                }
+            }
+        }
+    }
+
+    match cached {
+        Some((data, Freshness::Fresh)) => {
+            counter.hit();
+            Ok(data)
+        }
+        Some((data, _)) => {
+            counter.hit();
+            let guard = match begin_refresh(policy, &key) {
+                Some(guard) => guard,
+                None => {
+                    debug!("Background update already running for {}:{}", policy.name, key.row_id());
+                    return Ok(data);
+                }
+            };
+            let app_clone = app.clone();
+            tokio::spawn(async move {
+                // 任务结束时释放占位
+                let _guard = guard;
+                match fetch_and_store(&app_clone, policy, &key, fetch).await {
+                    Ok(new_data) => {
+                        info!("Background update success for {}", key.row_id());
+                        emit_update(&app_clone, policy, &key, &new_data);
+                    }
+                    Err(e) => error!("Background update failed for {}: {}", key.row_id(), e),
+                }
+            });
+            Ok(data)
+        }
+        None => {
+            counter.miss();
+            // 并发的未命中共享同一次请求，结果以 JSON 形式传给等待者
+            let flight_key = format!("{}:{}", policy.name, key.row_id());
+            let shared = FETCH_FLIGHTS
+                .run(flight_key, || async {
+                    let data = fetch_and_store(app, policy, &key, fetch).await?;
+                    serde_json::to_value(&data).map_err(|_| 500u16)
+                })
+                .await?;
+            serde_json::from_value::<T>(shared).map_err(|e| {
+                error!("解析 {} 失败: {:?}", key.row_id(), e);
+                500
+            })
+        }
+    }
+}
+
+// 陈旧命中时占住后台刷新，同一个 key 已经在刷新时返回 None
+fn begin_refresh(policy: &CachePolicy, key: &CacheKey) -> Option<InFlightGuard<'static>> {
+    REFRESHING.try_begin(&format!("{}:{}", policy.name, key.row_id()))
+}
+
+async fn fetch_and_store<T, F, Fut>(app: &AppHandle, policy: &CachePolicy, key: &CacheKey, fetch: F) -> Result<T, u16>
+where
+    T: Serialize,
+    F: FnOnce(AppHandle) -> Fut,
+    Fut: Future<Output = Result<T, u16>>,
+{
+    let data = fetch(app.clone()).await?;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        match serde_json::to_value(&data) {
+            Ok(value) => {
+                let (ttl, _) = policy.effective();
+                cache::write_cache_entry(&pool.0, policy.store, key, &value, ttl).await;
+            }
+            Err(e) => error!("序列化 {} 失败: {:?}", key.row_id(), e),
+        }
+    }
+    Ok(data)
+}
+
+// 与前端约定的事件格式：媒体为 {type, id, data}，其余为 {key, data}
+fn emit_update<T: Serialize>(app: &AppHandle, policy: &CachePolicy, key: &CacheKey, data: &T) {
+    let payload = match key {
+        CacheKey::Media { media_type, trakt_id } => serde_json::json!({
+            "type": media_type,
+            "id": trakt_id,
+            "data": data
+        }),
+        CacheKey::Key(key) => serde_json::json!({
+            "key": key,
+            "data": data
+        }),
+    };
+    let _ = app.emit(policy.event, payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::policy;
+
+    #[test]
+    fn stale_reads_of_one_key_start_one_refresh() {
+        let key = CacheKey::Key("stats_refresh_test".to_string());
+        let first = begin_refresh(&policy::USER_STATS, &key);
+        assert!(first.is_some());
+        // 第一次刷新还没结束，第二次陈旧读取不再发起刷新
+        assert!(begin_refresh(&policy::USER_STATS, &key).is_none());
+        // 其它 key 和其它策略不受影响
+        assert!(begin_refresh(&policy::USER_STATS, &CacheKey::Key("stats_other_test".to_string())).is_some());
+        assert!(begin_refresh(&policy::USER_WATCHED, &key).is_some());
+
+        drop(first);
+        assert!(begin_refresh(&policy::USER_STATS, &key).is_some());
+    }
+}
@@ -1,10 +1,11 @@
 use crate::model::movie::Movie;
 use crate::model::shows::{Episode, Show};
-use crate::trakt_api::ApiClient;
+use crate::trakt_api::cached_fetch::REFRESHING;
+use crate::trakt_api::hidden::hidden_ids;
+use crate::trakt_api::api_client;
 use crate::trakt_api::API;
 use serde::{Deserialize, Serialize};
 use tauri::{command, AppHandle, Manager, Emitter};
-use tokio::sync::Mutex;
 use crate::db::{DbPool, cache};
 use log::{info, error};
 use chrono::{NaiveDate, Duration};
@@ -25,12 +26,18 @@ pub struct CalendarShow {
 
 pub trait CalendarItemDate {
     fn get_date_str(&self) -> Option<String>;
+    // (类型, trakt id)，用于过滤隐藏条目
+    fn media_key(&self) -> (String, u32);
 }
 
 impl CalendarItemDate for CalendarMovie {
     fn get_date_str(&self) -> Option<String> {
         self.released.clone()
     }
+
+    fn media_key(&self) -> (String, u32) {
+        ("movie".to_string(), self.movie.ids.trakt)
+    }
 }
 
 impl CalendarItemDate for CalendarShow {
@@ -39,6 +46,10 @@ impl CalendarItemDate for CalendarShow {
         // 截取前10位 YYYY-MM-DD
         self.first_aired.as_ref().map(|s| s.chars().take(10).collect())
     }
+
+    fn media_key(&self) -> (String, u32) {
+        ("show".to_string(), self.show.ids.trakt)
+    }
 }
 
 #[command]
@@ -121,6 +132,56 @@ pub async fn get_my_calendar_shows(
     ).await
 }
 
+// DVD / 蓝光等家庭版发行日历，released 为发行日期
+#[command]
+pub async fn get_calendar_dvd(
+    app: AppHandle,
+    start_date: String,
+    days: u32,
+) -> Result<Vec<CalendarMovie>, u16> {
+    handle_calendar_request(
+        &app, 
+        "calendar_dvd", 
+        &start_date, 
+        days,
+        API.calendars.dvd.method.as_str(),
+        API.calendars.dvd.uri.clone()
+    ).await
+}
+
+#[command]
+pub async fn get_my_calendar_movies(
+    app: AppHandle,
+    start_date: String,
+    days: u32,
+) -> Result<Vec<CalendarMovie>, u16> {
+    handle_calendar_request(
+        &app, 
+        "calendar_my_movies", 
+        &start_date, 
+        days,
+        API.calendars.my_movies.method.as_str(),
+        API.calendars.my_movies.uri.clone()
+    ).await
+}
+
+// 仅包含用户收藏/待看列表中的电影
+#[command]
+pub async fn get_my_calendar_dvd(
+    app: AppHandle,
+    start_date: String,
+    days: u32,
+) -> Result<Vec<CalendarMovie>, u16> {
+    handle_calendar_request(
+        &app, 
+        "calendar_my_dvd", 
+        &start_date, 
+        days,
+        API.calendars.my_dvd.method.as_str(),
+        API.calendars.my_dvd.uri.clone()
+    ).await
+}
+
 // 泛型处理函数
 async fn handle_calendar_request<T>(
     app: &AppHandle,
@@ -224,16 +285,26 @@ where
         }
     }
 
-    // 3. 后台刷新陈旧数据
+    // 3. 后台刷新陈旧数据，已经在后台刷新的区间跳过
+    let stale_ranges: Vec<_> = stale_ranges
+        .into_iter()
+        .filter_map(|(range_start, range_days)| {
+            let flight_key = format!("calendar:{}_{}_{}", cache_prefix, range_start.format("%Y-%m-%d"), range_days);
+            REFRESHING.try_begin(&flight_key).map(|guard| (range_start, range_days, guard))
+        })
+        .collect();
     if !stale_ranges.is_empty() {
-        info!("Calendar stale ranges to refresh: {:?}", stale_ranges);
+        info!(
+            "Calendar stale ranges to refresh: {:?}",
+            stale_ranges.iter().map(|(start, days, _)| (start, days)).collect::<Vec<_>>()
+        );
         let app_clone = app.clone();
         let cache_prefix = cache_prefix.to_string();
         let method = method.to_string();
         let uri_template = uri_template.clone();
         
         tokio::spawn(async move {
-            for (range_start, range_days) in stale_ranges {
+            for (range_start, range_days, _guard) in stale_ranges {
                  let range_start_str = range_start.format("%Y-%m-%d").to_string();
                  match fetch_and_cache_range::<T>(&app_clone, &cache_prefix, &range_start_str, range_days, &method, uri_template.clone()).await {
                     Ok(_) => {
@@ -245,7 +316,13 @@ where
         });
     }
 
-    // 4. 结果去重和排序
+    // 4. 过滤隐藏条目 (不写入缓存，取消隐藏后立即生效)
+    let hidden = hidden_ids(app, &["calendar"]).await;
+    if !hidden.is_empty() {
+        final_results.retain(|item| !hidden.contains(&item.media_key()));
+    }
+
+    // 5. 结果去重和排序
     final_results.sort_by(|a, b| {
         let da = a.get_date_str().unwrap_or_default();
         let db = b.get_date_str().unwrap_or_default();
@@ -267,11 +344,7 @@ async fn fetch_and_cache_range<T>(
 where
     T: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static + CalendarItemDate + Clone,
 {
-    let client_state = app.state::<Mutex<ApiClient>>();
-    let mut client_clone = {
-        let client = client_state.lock().await;
-        client.clone()
-    };
+    let mut client_clone = api_client(app).await;
     
     // 构造请求 URI (动态替换 start_date 和 days)
     let uri = uri_template.replace("start_date", start_date).replace("days", &days.to_string());
@@ -0,0 +1,185 @@
+use crate::db::cache::CacheKey;
+use crate::db::{cache, policy, DbPool};
+use crate::model::movie::Movie;
+use crate::model::shows::{Season, Show};
+use crate::model::user::UserSummary;
+use crate::trakt_api::cached_fetch::cached_fetch;
+use crate::trakt_api::sync::SyncResponse;
+use crate::trakt_api::{api_client, Entry, API};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use tauri::{command, AppHandle, Manager};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HiddenItem {
+    pub hidden_at: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub movie: Option<Movie>,
+    pub show: Option<Show>,
+    pub season: Option<Season>,
+    pub user: Option<UserSummary>,
+}
+
+impl HiddenItem {
+    /// (类型, trakt id)，用于本地过滤；被隐藏的用户没有 trakt id，返回 None
+    pub fn media_key(&self) -> Option<(String, u32)> {
+        match self.item_type.as_str() {
+            "movie" => self.movie.as_ref().map(|m| ("movie".to_string(), m.ids.trakt)),
+            "show" => self.show.as_ref().map(|s| ("show".to_string(), s.ids.trakt)),
+            "season" => self.season.as_ref().map(|s| ("season".to_string(), s.ids.trakt)),
+            _ => None,
+        }
+    }
+}
+
+pub const HIDDEN_SECTIONS: [&str; 6] = [
+    "calendar",
+    "progress_watched",
+    "progress_collected",
+    "recommendations",
+    "comments",
+    "dropped",
+];
+
+// 隐藏列表分页拉取，防止异常数据导致无限请求
+const HIDDEN_PAGE_LIMIT: u32 = 100;
+const HIDDEN_MAX_PAGES: u32 = 20;
+
+#[command]
+pub async fn get_hidden_items(app: AppHandle, section: String) -> Result<Vec<HiddenItem>, u16> {
+    if !HIDDEN_SECTIONS.contains(&section.as_str()) {
+        return Err(400);
+    }
+    let cache_key = format!("hidden_{}", section);
+    cached_fetch(&app, &policy::HIDDEN_ITEMS, CacheKey::Key(cache_key), move |app| async move {
+        fetch_hidden(&app, &section).await
+    })
+    .await
+}
+
+async fn fetch_hidden(app: &AppHandle, section: &str) -> Result<Vec<HiddenItem>, u16> {
+    let mut client = api_client(app).await;
+
+    if !client.authenticated {
+        return Err(401);
+    }
+
+    let uri = API.hidden.items.uri.replace("section", section);
+    let mut all_items: Vec<Value> = Vec::new();
+
+    for page in 1..=HIDDEN_MAX_PAGES {
+        let result = client
+            .req_api(
+                app,
+                API.hidden.items.method.as_str(),
+                uri.clone(),
+                None,
+                None,
+                Some(HIDDEN_PAGE_LIMIT),
+                Some(page),
+                false,
+            )
+            .await?;
+
+        let page_items = match result {
+            Value::Array(items) => items,
+            _ => Vec::new(),
+        };
+        let count = page_items.len() as u32;
+        all_items.extend(page_items);
+        if count < HIDDEN_PAGE_LIMIT {
+            break;
+        }
+    }
+
+    serde_json::from_value::<Vec<HiddenItem>>(Value::Array(all_items)).map_err(|e| {
+        error!("解析 hidden {} 失败: {:?}", section, e);
+        500
+    })
+}
+
+/// media_type 为 movie / show / season 时使用 trakt_id，为 user 时使用 username
+#[command]
+pub async fn add_hidden_item(
+    app: AppHandle,
+    section: String,
+    media_type: String,
+    trakt_id: u32,
+    username: Option<String>,
+) -> Result<SyncResponse, u16> {
+    update_hidden(&app, &API.hidden.add, &section, &media_type, trakt_id, username).await
+}
+
+#[command]
+pub async fn remove_hidden_item(
+    app: AppHandle,
+    section: String,
+    media_type: String,
+    trakt_id: u32,
+    username: Option<String>,
+) -> Result<SyncResponse, u16> {
+    update_hidden(&app, &API.hidden.remove, &section, &media_type, trakt_id, username).await
+}
+
+async fn update_hidden(
+    app: &AppHandle,
+    entry: &Entry,
+    section: &str,
+    media_type: &str,
+    trakt_id: u32,
+    username: Option<String>,
+) -> Result<SyncResponse, u16> {
+    if !HIDDEN_SECTIONS.contains(&section) {
+        return Err(400);
+    }
+
+    let body = match media_type {
+        "movie" => json!({ "movies": [{ "ids": { "trakt": trakt_id } }] }),
+        "show" => json!({ "shows": [{ "ids": { "trakt": trakt_id } }] }),
+        "season" => json!({ "seasons": [{ "ids": { "trakt": trakt_id } }] }),
+        "user" => match username {
+            Some(name) => json!({ "users": [{ "ids": { "slug": name } }] }),
+            None => return Err(400),
+        },
+        _ => return Err(400),
+    };
+
+    let result = {
+        let mut client = api_client(app).await;
+        let uri = entry.uri.replace("section", section);
+        client
+            .req_api(app, entry.method.as_str(), uri, None, Some(body), None, None, false)
+            .await?
+    };
+
+    // 隐藏列表变化后直接丢弃缓存，下次读取时重新拉取
+    if let Some(pool) = app.try_state::<DbPool>() {
+        cache::delete_user_data_cache(&pool.0, &format!("hidden_{}", section)).await;
+    }
+    info!("Hidden {} updated: {} {}", section, media_type, trakt_id);
+
+    serde_json::from_value::<SyncResponse>(result).map_err(|e| {
+        error!("解析 hidden 响应失败: {:?}", e);
+        500
+    })
+}
+
+/// 汇总若干 section 中被隐藏的 (类型, trakt id)
+/// 未登录或请求失败时返回空集合，不影响调用方的正常流程
+pub(crate) async fn hidden_ids(app: &AppHandle, sections: &[&str]) -> HashSet<(String, u32)> {
+    let mut ids = HashSet::new();
+    if !api_client(app).await.authenticated {
+        return ids;
+    }
+
+    for section in sections {
+        match get_hidden_items(app.clone(), section.to_string()).await {
+            Ok(items) => ids.extend(items.iter().filter_map(|item| item.media_key())),
+            Err(e) => error!("获取 hidden {} 失败: {}", section, e),
+        }
+    }
+    ids
+}
@@ -0,0 +1,92 @@
+use crate::db::{cache, DbPool};
+use crate::model::insights::{TitleInsights, TitleRatings, TitleStats};
+use crate::model::user::User;
+use crate::trakt_api::{api_client, Entry, API};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::{command, AppHandle, Manager};
+
+/// 详情页统计：stats、评分分布和正在观看的用户，一次调用返回
+/// media_type 为 movie / show / season / episode，季和集需要同时传入 season / episode
+#[command]
+pub async fn get_title_insights(
+    app: AppHandle,
+    media_type: String,
+    id: u32,
+    season: Option<u32>,
+    episode: Option<u32>,
+) -> Result<TitleInsights, u16> {
+    let media_path = match (media_type.as_str(), season, episode) {
+        ("movie", _, _) => format!("/movies/{}", id),
+        ("show", _, _) => format!("/shows/{}", id),
+        ("season", Some(s), _) => format!("/shows/{}/seasons/{}", id, s),
+        ("episode", Some(s), Some(e)) => format!("/shows/{}/seasons/{}/episodes/{}", id, s, e),
+        _ => return Err(400),
+    };
+    let cache_key = format!("api_insights{}", media_path.replace('/', "_"));
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
+            if let Ok(data) = serde_json::from_value::<TitleInsights>(json) {
+                return Ok(data);
+            }
+        }
+    }
+
+    // 三个请求互不依赖，并发执行
+    let stats = spawn_fetch(&app, &API.insights.stats, &media_path).await;
+    let ratings = spawn_fetch(&app, &API.insights.ratings, &media_path).await;
+    let watching = spawn_fetch(&app, &API.insights.watching, &media_path).await;
+
+    let stats = join_parsed::<TitleStats>(stats).await;
+    let ratings = join_parsed::<TitleRatings>(ratings).await;
+    let watching = join_parsed::<Vec<User>>(watching).await;
+
+    if let (Err(e), Err(_), Err(_)) = (&stats, &ratings, &watching) {
+        return Err(*e);
+    }
+    // 部分请求失败时结果不完整，不写缓存，下次重新请求
+    let complete = stats.is_ok() && ratings.is_ok() && watching.is_ok();
+
+    let insights = TitleInsights {
+        media_type,
+        id,
+        stats: stats.ok(),
+        ratings: ratings.ok(),
+        watching: watching.unwrap_or_default(),
+    };
+
+    if complete {
+        if let Some(pool) = app.try_state::<DbPool>() {
+            let json_data = serde_json::to_value(&insights).unwrap_or_default();
+            cache::set_api_response_cache_with_ttl(&pool.0, &cache_key, &json_data, cache::CACHE_TTL_INSIGHTS).await;
+        }
+    }
+
+    Ok(insights)
+}
+
+async fn spawn_fetch(
+    app: &AppHandle,
+    entry: &'static Entry,
+    media_path: &str,
+) -> tokio::task::JoinHandle<Result<Value, u16>> {
+    let app_handle = app.clone();
+    let mut client = api_client(app).await;
+    let uri = entry.uri.replace("media_path", media_path);
+
+    tokio::spawn(async move {
+        client
+            .req_api(&app_handle, entry.method.as_str(), uri, None, None, None, None, false)
+            .await
+    })
+}
+
+async fn join_parsed<T: DeserializeOwned>(handle: tokio::task::JoinHandle<Result<Value, u16>>) -> Result<T, u16> {
+    let value = handle.await.map_err(|_| 500u16)??;
+    serde_json::from_value::<T>(value).map_err(|e| {
+        error!("解析统计数据失败: {:?}", e);
+        500
+    })
+}
@@ -0,0 +1,91 @@
+use crate::db::{cache, DbPool};
+use crate::model::discovery::{Certifications, Country, Genre, Language, Network};
+use crate::trakt_api::{api_client, Entry, API};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::{command, AppHandle, Manager};
+
+// 过滤下拉框所需的元数据，几乎不会变化，缓存 30 天
+
+#[command]
+pub async fn get_genres(app: AppHandle, media_type: String) -> Result<Vec<Genre>, u16> {
+    let media_type = check_media_type(media_type)?;
+    fetch_meta(&app, &API.meta.genres, &format!("api_meta_genres_{}", media_type), Some(&media_type)).await
+}
+
+#[command]
+pub async fn get_certifications(app: AppHandle, media_type: String) -> Result<Certifications, u16> {
+    let media_type = check_media_type(media_type)?;
+    fetch_meta(&app, &API.meta.certifications, &format!("api_meta_certifications_{}", media_type), Some(&media_type)).await
+}
+
+#[command]
+pub async fn get_countries(app: AppHandle, media_type: String) -> Result<Vec<Country>, u16> {
+    let media_type = check_media_type(media_type)?;
+    fetch_meta(&app, &API.meta.countries, &format!("api_meta_countries_{}", media_type), Some(&media_type)).await
+}
+
+#[command]
+pub async fn get_languages(app: AppHandle, media_type: String) -> Result<Vec<Language>, u16> {
+    let media_type = check_media_type(media_type)?;
+    fetch_meta(&app, &API.meta.languages, &format!("api_meta_languages_{}", media_type), Some(&media_type)).await
+}
+
+#[command]
+pub async fn get_networks(app: AppHandle) -> Result<Vec<Network>, u16> {
+    fetch_meta(&app, &API.meta.networks, "api_meta_networks", None).await
+}
+
+// Trakt 的元数据接口使用复数形式 movies / shows
+fn check_media_type(media_type: String) -> Result<String, u16> {
+    match media_type.as_str() {
+        "movie" | "movies" => Ok("movies".to_string()),
+        "show" | "shows" => Ok("shows".to_string()),
+        _ => Err(400),
+    }
+}
+
+async fn fetch_meta<T>(
+    app: &AppHandle,
+    entry: &Entry,
+    cache_key: &str,
+    media_type: Option<&str>,
+) -> Result<T, u16>
+where
+    T: Serialize + DeserializeOwned,
+{
+    if let Some(pool) = app.try_state::<DbPool>() {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, cache_key).await {
+            if let Ok(data) = serde_json::from_value::<T>(json) {
+                return Ok(data);
+            }
+        }
+    }
+
+    let uri = match media_type {
+        Some(t) => entry.uri.replace("type", t),
+        None => entry.uri.clone(),
+    };
+
+    let mut client = api_client(app).await;
+    let result = client
+        .req_api(app, entry.method.as_str(), uri, None, None, None, None, false)
+        .await;
+
+    match result {
+        Ok(value) => match serde_json::from_value::<T>(value.clone()) {
+            Ok(data) => {
+                if let Some(pool) = app.try_state::<DbPool>() {
+                    cache::set_api_response_cache_with_ttl(&pool.0, cache_key, &value, cache::CACHE_TTL_LONG).await;
+                }
+                Ok(data)
+            }
+            Err(e) => {
+                error!("解析 {} 失败: {:?}", cache_key, e);
+                Err(500)
+            }
+        },
+        Err(status) => Err(status),
+    }
+}
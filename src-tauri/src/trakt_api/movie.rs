@@ -1,33 +1,41 @@
 use crate::model::movie::{
     MovieAnticipated, MovieDetails, MovieTrending, MovieTranslations, Movie,
-    MovieWatched, MovieCollected
+    MovieWatched, MovieCollected, MoviePlayed, MovieFavorited, MovieRecommended, MovieBoxOffice,
+    MovieRelease, MovieAlias, MovieLocalRelease
 };
+use crate::model::discovery::DiscoveryFilter;
 
 use tauri::command;
-use crate::trakt_api::{ApiClient, API};
-use tauri::{AppHandle, Manager, Emitter};
-use tokio::sync::Mutex;
+use crate::trakt_api::{api_client, cached_chart_request, Entry, API};
+use crate::trakt_api::cached_fetch::cached_fetch;
+use crate::settings::{get_app_config, normalize_country};
+use tauri::{AppHandle, Manager};
 use std::collections::HashMap;
-use crate::db::{DbPool, cache};
-use log::{info, error};
+use crate::db::{DbPool, cache, policy};
+use crate::db::cache::CacheKey;
+use crate::image_cache::prefetch;
+use log::error;
+
+// 批量获取本地发行信息时一次最多的条目数
+const MAX_LOCAL_RELEASE_BATCH: usize = 100;
 
 #[command]
-pub async fn movie_trending(app: AppHandle) -> Result<Vec<MovieTrending>, u16> {
-    let cache_key = "api_movie_trending";
+pub async fn movie_trending(app: AppHandle, filter: Option<DiscoveryFilter>) -> Result<Vec<MovieTrending>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_movie_trending{}", filter.cache_key());
     
     // 1. Check Cache
     if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(json) = cache::get_api_response_cache(&pool.0, cache_key).await {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<MovieTrending>>(json) {
                 return Ok(data);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let result = client
-        .req_api(&app, API.movie.trending.method.as_str(), API.movie.trending.uri.clone(), None, None, None, None, true)
+        .req_api(&app, API.movie.trending.method.as_str(), API.movie.trending.uri.clone(), Some(filter.to_params()), None, None, None, true)
         .await;
         
     match result {
@@ -36,7 +44,7 @@ pub async fn movie_trending(app: AppHandle) -> Result<Vec<MovieTrending>, u16> {
             
             // 2. Save Cache
             if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_api_response_cache(&pool.0, cache_key, &result).await;
+                cache::set_api_response_cache(&pool.0, &cache_key, &result).await;
             }
             
             Ok(movie_trending)
@@ -51,28 +59,24 @@ pub async fn movie_trending_page(
     page: u32, 
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<MovieTrending>, u16> {
-    let cache_key = format!("api_movie_trending_p{}_l{}_g{:?}_c{:?}", page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_movie_trending_p{}_l{}{}", page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<MovieTrending>>(json) {
+                prefetch_trending_posters(&app, &data);
                 return Ok(data);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
 
     let result = client
         .req_api(&app, API.movie.trending.method.as_str(), API.movie.trending.uri.clone(), Some(params), None, Some(limit), Some(page), true)
@@ -84,21 +88,32 @@ pub async fn movie_trending_page(
             if let Some(pool) = app.try_state::<DbPool>() {
                 cache::set_api_response_cache(&pool.0, &cache_key, &result).await;
             }
+            prefetch_trending_posters(&app, &movie_trending);
             Ok(movie_trending)
         }
         Err(e) => Err(e)
     }
 }
 
+fn prefetch_trending_posters(app: &AppHandle, items: &[MovieTrending]) {
+    let urls = items
+        .iter()
+        .filter_map(|item| item.movie.images.poster.first().cloned())
+        .collect();
+    prefetch::prefetch_posters(app, prefetch::MOVIE_TRENDING_GROUP, urls);
+}
+
 #[command]
 pub async fn movie_popular_page(
     app: AppHandle, 
     page: u32, 
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<Movie>, u16> {
-    let cache_key = format!("api_movie_popular_p{}_l{}_g{:?}_c{:?}", page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_movie_popular_p{}_l{}{}", page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -108,16 +123,9 @@ pub async fn movie_popular_page(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
 
     let result = client
         .req_api(&app, API.movie.popular.method.as_str(), API.movie.popular.uri.clone(), Some(params), None, Some(limit), Some(page), true)
@@ -136,8 +144,14 @@ pub async fn movie_popular_page(
 }
 
 #[command]
-pub async fn movie_anticipated(app: AppHandle, page: u32, limit: u32) -> Result<Vec<MovieAnticipated>, u16> {
-    let cache_key = format!("api_movie_anticipated_p{}_l{}", page, limit);
+pub async fn movie_anticipated(
+    app: AppHandle,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<MovieAnticipated>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_movie_anticipated_p{}_l{}{}", page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -147,10 +161,9 @@ pub async fn movie_anticipated(app: AppHandle, page: u32, limit: u32) -> Result<
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let result = client
-        .req_api(&app, API.movie.anticipated.method.as_str(), API.movie.anticipated.uri.clone(), None, None, Some(limit), Some(page), true)
+        .req_api(&app, API.movie.anticipated.method.as_str(), API.movie.anticipated.uri.clone(), Some(filter.to_params()), None, Some(limit), Some(page), true)
         .await;
         
     match result {
@@ -167,52 +180,14 @@ pub async fn movie_anticipated(app: AppHandle, page: u32, limit: u32) -> Result<
 
 #[command]
 pub async fn movie_details(app: AppHandle, id: u32) -> Result<MovieDetails, u16> {
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    // 1. 尝试从缓存获取
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_media_cache(&pool.0, "movie", id).await {
-            if let Ok(details) = serde_json::from_value::<MovieDetails>(result.data) {
-                cache_data = Some(details);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
-
-    // 2. 如果有缓存且新鲜，直接返回
-    if let Some(data) = cache_data.clone() {
-        if !should_fetch {
-            return Ok(data);
-        }
-        // 如果数据陈旧 (stale)，启动后台刷新任务，但先返回旧数据
-        let app_clone = app.clone();
-        tokio::spawn(async move {
-            match fetch_and_cache_movie_details(&app_clone, id).await {
-                Ok(new_data) => {
-                    info!("Background update success for movie {}", id);
-                    // 通知前端刷新
-                    let _ = app_clone.emit("media-update", serde_json::json!({
-                        "type": "movie",
-                        "id": id,
-                        "data": new_data
-                    }));
-                },
-                Err(e) => error!("Background update failed for movie {}: {}", id, e),
-            }
-        });
-        
-        return Ok(data);
-    }
-
-    // 3. 缓存未命中，同步请求
-    fetch_and_cache_movie_details(&app, id).await
+    cached_fetch(&app, &policy::MOVIE_DETAILS, CacheKey::media("movie", id), move |app| async move {
+        fetch_movie_details(&app, id).await
+    })
+    .await
 }
 
-// 辅助函数：请求并缓存
-async fn fetch_and_cache_movie_details(app: &AppHandle, id: u32) -> Result<MovieDetails, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+async fn fetch_movie_details(app: &AppHandle, id: u32) -> Result<MovieDetails, u16> {
+    let mut client = api_client(app).await;
     
     let mut uri = API.movie.details.uri.clone();
     uri = uri.replace("id", &id.to_string());
@@ -220,26 +195,16 @@ async fn fetch_and_cache_movie_details(app: &AppHandle, id: u32) -> Result<Movie
     let mut params = HashMap::new();
     params.insert("extended".to_string(), "full".to_string());
     
-    let result = client.req_api(app, API.movie.details.method.as_str(), uri, Some(params), None, None, None, true).await;
-    
-    match result {
-        Ok(result) => {
-            let movie_details = serde_json::from_value::<MovieDetails>(result.clone()).unwrap();
-            
-            if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_media_cache(&pool.0, "movie", id, &result, cache::CACHE_TTL_SHORT).await;
-            }
-            
-            Ok(movie_details)
-        }
-        Err(e) => Err(e)
-    }
+    let result = client.req_api(app, API.movie.details.method.as_str(), uri, Some(params), None, None, None, true).await?;
+    serde_json::from_value::<MovieDetails>(result).map_err(|e| {
+        error!("解析电影详情失败: {:?}", e);
+        500
+    })
 }
 
 #[command]
 pub async fn movie_translation(app: AppHandle, id: u32, language: String) -> Result<MovieTranslations, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let mut uri = API.movie.trans.uri.clone();
     uri = uri.replace("id", &id.to_string()).replace("language", &language);
     let result = client.req_api(&app, API.movie.trans.method.as_str(), uri, None, None, None, None, true).await;
@@ -258,9 +223,11 @@ pub async fn movie_watched_period(
     page: u32,
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<MovieWatched>, u16> {
-    let cache_key = format!("api_movie_watched_{}_p{}_l{}_g{:?}_c{:?}", period, page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_movie_watched_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -270,19 +237,12 @@ pub async fn movie_watched_period(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
     let mut uri = API.movie.watched.uri.clone();
     uri = uri.replace("period", &period);
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
     
     let result = client
         .req_api(
@@ -318,9 +278,11 @@ pub async fn movie_collected_period(
     page: u32,
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<MovieCollected>, u16> {
-    let cache_key = format!("api_movie_collected_{}_p{}_l{}_g{:?}_c{:?}", period, page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_movie_collected_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -330,19 +292,12 @@ pub async fn movie_collected_period(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
     let mut uri = API.movie.collected.uri.clone();
     uri = uri.replace("period", &period);
 
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
     
     let result = client
         .req_api(
@@ -370,3 +325,172 @@ pub async fn movie_collected_period(
         Err(e) => Err(e)
     }
 }
+
+#[command]
+pub async fn movie_played_period(
+    app: AppHandle,
+    period: String,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<MoviePlayed>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_movie_played_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
+    let uri = API.movie.played.uri.replace("period", &period);
+
+    cached_chart_request(&app, &API.movie.played, uri, &cache_key, filter.to_params(), Some(limit), Some(page)).await
+}
+
+#[command]
+pub async fn movie_favorited_period(
+    app: AppHandle,
+    period: String,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<MovieFavorited>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_movie_favorited_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
+    let uri = API.movie.favorited.uri.replace("period", &period);
+
+    cached_chart_request(&app, &API.movie.favorited, uri, &cache_key, filter.to_params(), Some(limit), Some(page)).await
+}
+
+#[command]
+pub async fn movie_recommended_period(
+    app: AppHandle,
+    period: String,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<MovieRecommended>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_movie_recommended_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
+    let uri = API.movie.recommended.uri.replace("period", &period);
+
+    cached_chart_request(&app, &API.movie.recommended, uri, &cache_key, filter.to_params(), Some(limit), Some(page)).await
+}
+
+/// 北美周末票房前 10，Trakt 不支持分页和过滤
+#[command]
+pub async fn movie_boxoffice(app: AppHandle) -> Result<Vec<MovieBoxOffice>, u16> {
+    cached_chart_request(
+        &app,
+        &API.movie.boxoffice,
+        API.movie.boxoffice.uri.clone(),
+        "api_movie_boxoffice",
+        HashMap::new(),
+        None,
+        None,
+    ).await
+}
+
+/// 指定国家的发行日期和分级，country 为空时使用设置中的 home_country
+#[command]
+pub async fn movie_releases(app: AppHandle, id: u32, country: Option<String>) -> Result<Vec<MovieRelease>, u16> {
+    let country = match country {
+        Some(c) if !c.is_empty() => normalize_country(&c).ok_or(400u16)?,
+        _ => home_country(&app).await,
+    };
+    let uri = API.movie.releases.uri.replace("id", &id.to_string()).replace("country", &country);
+    cached_media_list(&app, &API.movie.releases, uri, &format!("movie_releases_{}", country), id).await
+}
+
+#[command]
+pub async fn movie_aliases(app: AppHandle, id: u32) -> Result<Vec<MovieAlias>, u16> {
+    let uri = API.movie.aliases.uri.replace("id", &id.to_string());
+    cached_media_list(&app, &API.movie.aliases, uri, "movie_aliases", id).await
+}
+
+/// 本地化发行信息：本国发行日期、本国分级和本国片名
+#[command]
+pub async fn movie_local_release(app: AppHandle, id: u32) -> Result<MovieLocalRelease, u16> {
+    let country = home_country(&app).await;
+    let releases = movie_releases(app.clone(), id, Some(country.clone())).await?;
+    // 片名别名不是必须的，失败时忽略
+    let aliases = movie_aliases(app.clone(), id).await.unwrap_or_default();
+
+    // 优先使用院线上映的分级
+    let certification = releases
+        .iter()
+        .filter(|r| r.certification.as_deref().is_some_and(|c| !c.is_empty()))
+        .min_by_key(|r| if r.release_type.as_deref() == Some("theatrical") { 0 } else { 1 })
+        .and_then(|r| r.certification.clone());
+    let title = aliases
+        .into_iter()
+        .find(|a| a.country == country)
+        .map(|a| a.title);
+
+    Ok(MovieLocalRelease {
+        country,
+        certification,
+        title,
+        releases,
+    })
+}
+
+/// 日历等列表批量获取本地化发行信息，key 为 trakt id，获取失败的条目不返回
+/// 数据缓存在 media_cache，列表中大部分条目通常已命中
+#[command]
+pub async fn movie_local_releases(app: AppHandle, ids: Vec<u32>) -> Result<HashMap<u32, MovieLocalRelease>, u16> {
+    if ids.len() > MAX_LOCAL_RELEASE_BATCH {
+        return Err(400);
+    }
+
+    let mut result = HashMap::new();
+    for id in ids {
+        if result.contains_key(&id) {
+            continue;
+        }
+        match movie_local_release(app.clone(), id).await {
+            Ok(release) => {
+                result.insert(id, release);
+            }
+            Err(e) => error!("获取 {} 的本地发行信息失败: {}", id, e),
+        }
+    }
+    Ok(result)
+}
+
+async fn home_country(app: &AppHandle) -> String {
+    match get_app_config(app.clone()).await {
+        Ok(conf) => conf.home_country,
+        Err(_) => "us".to_string(),
+    }
+}
+
+// 发行信息变化很少，缓存在 media_cache；刷新失败时退回陈旧数据
+async fn cached_media_list<T>(app: &AppHandle, entry: &Entry, uri: String, media_type: &str, id: u32) -> Result<Vec<T>, u16>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let mut stale_data = None;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        if let Some(result) = cache::get_media_cache(&pool.0, media_type, id).await {
+            if let Ok(data) = serde_json::from_value::<Vec<T>>(result.data) {
+                if !result.is_stale {
+                    return Ok(data);
+                }
+                stale_data = Some(data);
+            }
+        }
+    }
+
+    let mut client = api_client(app).await;
+    let result = client.req_api(app, entry.method.as_str(), uri, None, None, None, None, false).await;
+
+    match result.and_then(|value| {
+        serde_json::from_value::<Vec<T>>(value.clone()).map(|data| (value, data)).map_err(|e| {
+            error!("解析 {}_{} 失败: {:?}", media_type, id, e);
+            500
+        })
+    }) {
+        Ok((value, data)) => {
+            if let Some(pool) = app.try_state::<DbPool>() {
+                cache::set_media_cache(&pool.0, media_type, id, &value, cache::CACHE_TTL_SHORT).await;
+            }
+            Ok(data)
+        }
+        Err(e) => stale_data.ok_or(e),
+    }
+}
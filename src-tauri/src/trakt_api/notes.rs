@@ -0,0 +1,267 @@
+use crate::db::notes::{self as notes_db, NoteTarget};
+use crate::db::DbPool;
+use crate::model::notes::{LocalNote, NewNote, Note, NoteItem};
+use crate::trakt_api::{api_client, API};
+use log::{error, info};
+use serde_json::{json, Value};
+use tauri::{command, AppHandle, Manager};
+
+const NOTE_MEDIA_TYPES: [&str; 5] = ["movie", "show", "season", "episode", "person"];
+const NOTE_LIST_TYPES: [&str; 6] = ["all", "movies", "shows", "seasons", "episodes", "people"];
+const NOTE_ATTACHMENTS: [&str; 3] = ["history", "collection", "rating"];
+const NOTE_PRIVACY: [&str; 3] = ["private", "friends", "public"];
+// Trakt 限制单条笔记 500 字
+const MAX_NOTE_LENGTH: usize = 500;
+const NOTES_PAGE_LIMIT: u32 = 100;
+const NOTES_MAX_PAGES: u32 = 20;
+// 自己新增的笔记在本地镜像中的 owner
+const OWN_NOTES: &str = "me";
+
+/// 获取用户的全部笔记，同时写入本地镜像
+#[command]
+pub async fn get_notes(
+    app: AppHandle,
+    id: String,
+    select_type: Option<String>,
+) -> Result<Vec<NoteItem>, u16> {
+    let select_type = select_type.unwrap_or_else(|| "all".to_string());
+    if !NOTE_LIST_TYPES.contains(&select_type.as_str()) {
+        return Err(400);
+    }
+
+    let mut client = api_client(&app).await;
+    let uri = API.notes.list.uri.replace("id", &id).replace("type", &select_type);
+
+    let mut raw_items: Vec<Value> = Vec::new();
+    // 超过页数上限时列表不完整，不能据此删除本地镜像
+    let mut complete = false;
+    for page in 1..=NOTES_MAX_PAGES {
+        let result = client
+            .req_api(
+                &app,
+                API.notes.list.method.as_str(),
+                uri.clone(),
+                API.notes.list.params.clone(),
+                None,
+                Some(NOTES_PAGE_LIMIT),
+                Some(page),
+                false,
+            )
+            .await?;
+
+        let page_items = match result {
+            Value::Array(items) => items,
+            _ => Vec::new(),
+        };
+        let count = page_items.len() as u32;
+        raw_items.extend(page_items);
+        if count < NOTES_PAGE_LIMIT {
+            complete = true;
+            break;
+        }
+    }
+    drop(client);
+
+    let mut items = Vec::new();
+    for raw in raw_items {
+        match serde_json::from_value::<NoteItem>(raw.clone()) {
+            Ok(item) => {
+                if let (Some(pool), Some(trakt_id)) = (app.try_state::<DbPool>(), item.trakt_id()) {
+                    let attached = item.attached_to.as_ref();
+                    let target = NoteTarget {
+                        media_type: &item.item_type,
+                        trakt_id,
+                        attached_to: attached.map(|a| a.attachment_type.as_str()),
+                        attached_id: attached.and_then(|a| a.id),
+                    };
+                    notes_db::upsert_note(&pool.0, &id, &item.note, &target, Some(&raw)).await;
+                }
+                items.push(item);
+            }
+            Err(e) => error!("解析笔记失败: {:?}", e),
+        }
+    }
+
+    if complete {
+        if let Some(pool) = app.try_state::<DbPool>() {
+            let keep: Vec<u64> = items.iter().map(|item| item.note.id).collect();
+            let removed = notes_db::prune_notes(&pool.0, &id, note_media_type(&select_type), &keep).await;
+            if removed > 0 {
+                info!("Removed {} notes of {} deleted elsewhere", removed, id);
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// 给条目添加笔记，attached_to 可选 history / collection / rating，需同时传入 attached_id
+#[command]
+pub async fn add_note(app: AppHandle, note: NewNote) -> Result<Note, u16> {
+    let NewNote {
+        media_type,
+        trakt_id,
+        notes,
+        spoiler,
+        privacy,
+        attached_to,
+        attached_id,
+    } = note;
+    if !NOTE_MEDIA_TYPES.contains(&media_type.as_str()) {
+        return Err(400);
+    }
+    let notes = check_note(&notes, privacy.as_deref())?;
+
+    let mut body = json!({
+        media_type.as_str(): { "ids": { "trakt": trakt_id } },
+        "notes": notes,
+        "spoiler": spoiler.unwrap_or(false),
+        "privacy": privacy.unwrap_or_else(|| "private".to_string()),
+    });
+    if let Some(attachment) = &attached_to {
+        if !NOTE_ATTACHMENTS.contains(&attachment.as_str()) {
+            return Err(400);
+        }
+        body["attached_to"] = json!({ "type": attachment, "id": attached_id });
+    }
+
+    let result = {
+        let mut client = api_client(&app).await;
+        client
+            .req_api(
+                &app,
+                API.notes.add.method.as_str(),
+                API.notes.add.uri.clone(),
+                None,
+                Some(body),
+                None,
+                None,
+                false,
+            )
+            .await?
+    };
+
+    let note = parse_note(result)?;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        let target = NoteTarget {
+            media_type: &media_type,
+            trakt_id,
+            attached_to: attached_to.as_deref(),
+            attached_id,
+        };
+        notes_db::upsert_note(&pool.0, OWN_NOTES, &note, &target, None).await;
+    }
+    info!("Note {} added to {} {}", note.id, media_type, trakt_id);
+    Ok(note)
+}
+
+#[command]
+pub async fn update_note(
+    app: AppHandle,
+    note_id: u64,
+    notes: String,
+    spoiler: Option<bool>,
+    privacy: Option<String>,
+) -> Result<Note, u16> {
+    let notes = check_note(&notes, privacy.as_deref())?;
+
+    let mut body = json!({ "notes": notes });
+    if let Some(spoiler) = spoiler {
+        body["spoiler"] = json!(spoiler);
+    }
+    if let Some(privacy) = privacy {
+        body["privacy"] = json!(privacy);
+    }
+
+    let result = {
+        let mut client = api_client(&app).await;
+        let uri = API.notes.update.uri.replace("note_id", &note_id.to_string());
+        client
+            .req_api(&app, API.notes.update.method.as_str(), uri, None, Some(body), None, None, false)
+            .await?
+    };
+
+    let note = parse_note(result)?;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        notes_db::update_note_content(&pool.0, &note).await;
+    }
+    Ok(note)
+}
+
+#[command]
+pub async fn delete_note(app: AppHandle, note_id: u64) -> Result<(), u16> {
+    {
+        let mut client = api_client(&app).await;
+        let uri = API.notes.delete.uri.replace("note_id", &note_id.to_string());
+        client
+            .req_api(&app, API.notes.delete.method.as_str(), uri, None, None, None, None, false)
+            .await?;
+    }
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+        notes_db::delete_note(&pool.0, note_id).await;
+    }
+    Ok(())
+}
+
+/// 在本地镜像中按内容搜索笔记，不需要联网
+#[command]
+pub async fn search_notes(
+    app: AppHandle,
+    query: String,
+    media_type: Option<String>,
+) -> Result<Vec<LocalNote>, String> {
+    if let Some(pool) = app.try_state::<DbPool>() {
+        Ok(notes_db::search_notes(&pool.0, query.trim(), media_type.as_deref()).await)
+    } else {
+        Err("Database not initialized".to_string())
+    }
+}
+
+/// 本地镜像中某个条目上的笔记，供详情页展示
+#[command]
+pub async fn get_item_notes(
+    app: AppHandle,
+    media_type: String,
+    trakt_id: u32,
+) -> Result<Vec<LocalNote>, String> {
+    if let Some(pool) = app.try_state::<DbPool>() {
+        Ok(notes_db::get_notes_for_item(&pool.0, &media_type, trakt_id).await)
+    } else {
+        Err("Database not initialized".to_string())
+    }
+}
+
+// 校验通过时返回去掉首尾空白的内容，发送给 Trakt 的也是这个值
+fn check_note<'a>(notes: &'a str, privacy: Option<&str>) -> Result<&'a str, u16> {
+    let notes = notes.trim();
+    let len = notes.chars().count();
+    if len == 0 || len > MAX_NOTE_LENGTH {
+        return Err(400);
+    }
+    if let Some(p) = privacy {
+        if !NOTE_PRIVACY.contains(&p) {
+            return Err(400);
+        }
+    }
+    Ok(notes)
+}
+
+// 列表类型对应的本地 media_type，all 返回 None
+fn note_media_type(select_type: &str) -> Option<&'static str> {
+    match select_type {
+        "movies" => Some("movie"),
+        "shows" => Some("show"),
+        "seasons" => Some("season"),
+        "episodes" => Some("episode"),
+        "people" => Some("person"),
+        _ => None,
+    }
+}
+
+fn parse_note(value: Value) -> Result<Note, u16> {
+    serde_json::from_value::<Note>(value).map_err(|e| {
+        error!("解析笔记响应失败: {:?}", e);
+        500
+    })
+}
@@ -1,11 +1,24 @@
-use crate::db::{DbPool, cache};
-use crate::model::shows::{Show, ShowProgress, ShowProgressSummary, UpNextItem};
-use crate::trakt_api::ApiClient;
+use crate::db::policy;
+use crate::db::cache::CacheKey;
+use crate::image_cache::prefetch;
+use crate::trakt_api::cached_fetch::cached_fetch;
+use crate::model::shows::{
+    MissingEpisode, MissingEpisodesItem, Show, ShowProgress, ShowProgressSummary, UpNextItem,
+};
+use crate::trakt_api::hidden::hidden_ids;
+use crate::trakt_api::sync::{get_playback_progress, PlaybackItem};
+use crate::trakt_api::user::get_collection;
+use crate::trakt_api::{api_client, Entry};
 use crate::trakt_api::API;
 use log::{info, error};
 use serde::{Deserialize, Serialize};
-use tauri::{command, AppHandle, Manager, Emitter};
-use tokio::sync::Mutex;
+use std::sync::Arc;
+use tauri::{command, AppHandle};
+use tokio::sync::Semaphore;
+
+// 计算缺失剧集时并发请求收藏进度的上限
+const MISSING_EPISODES_CONCURRENCY: usize = 4;
+const DEFAULT_UP_NEXT_LIMIT: usize = 20;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WatchedShow {
@@ -15,17 +28,35 @@ pub struct WatchedShow {
     pub show: Show,
 }
 
+/// 首页 "继续观看"：暂停进度 (resume) 与下一集 (up_next) 合并后的条目
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContinueWatchingItem {
+    pub kind: String,
+    pub sort_at: Option<String>,
+    pub playback: Option<PlaybackItem>,
+    pub up_next: Option<UpNextItem>,
+}
+
 #[command]
 pub async fn get_show_progress(app: AppHandle, id: u32) -> Result<ShowProgress, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    fetch_progress(&app, &API.shows.progress, id).await
+}
+
+/// 收藏进度，completed 表示已收藏的集数
+#[command]
+pub async fn get_show_collection_progress(app: AppHandle, id: u32) -> Result<ShowProgress, u16> {
+    fetch_progress(&app, &API.shows.collection_progress, id).await
+}
+
+async fn fetch_progress(app: &AppHandle, entry: &Entry, id: u32) -> Result<ShowProgress, u16> {
+    let mut client = api_client(app).await;
 
-    let uri = API.shows.progress.uri.replace("id", &id.to_string());
+    let uri = entry.uri.replace("id", &id.to_string());
 
     let result = client
         .req_api(
-            &app,
-            API.shows.progress.method.as_str(),
+            app,
+            entry.method.as_str(),
             uri,
             None,
             None,
@@ -47,6 +78,96 @@ pub async fn get_show_progress(app: AppHandle, id: u32) -> Result<ShowProgress,
     }
 }
 
+/// 所有收藏剧集中已播出但未收藏的剧集
+#[command]
+pub async fn get_missing_episodes(app: AppHandle, username: String) -> Result<Vec<MissingEpisodesItem>, u16> {
+    let cache_key = format!("missing_episodes_{}", username);
+    cached_fetch(&app, &policy::MISSING_EPISODES, CacheKey::Key(cache_key), move |app| async move {
+        fetch_missing_episodes(&app, &username).await
+    })
+    .await
+}
+
+async fn fetch_missing_episodes(app: &AppHandle, username: &str) -> Result<Vec<MissingEpisodesItem>, u16> {
+    let collection = get_collection(app.clone(), username.to_string(), "shows".to_string()).await?;
+
+    let hidden = hidden_ids(app, &["progress_collected"]).await;
+    let shows: Vec<Show> = collection
+        .into_iter()
+        .filter_map(|item| item.show)
+        .filter(|show| !hidden.contains(&("show".to_string(), show.ids.trakt)))
+        .collect();
+
+    let semaphore = Arc::new(Semaphore::new(MISSING_EPISODES_CONCURRENCY));
+    let mut handles = Vec::new();
+
+    for show in shows {
+        let app_handle = app.clone();
+        let semaphore = semaphore.clone();
+        let client_clone = api_client(app).await;
+
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.ok()?;
+            let mut client = client_clone;
+            let uri = API
+                .shows
+                .collection_progress
+                .uri
+                .replace("id", &show.ids.trakt.to_string());
+
+            let value = client
+                .req_api(
+                    &app_handle,
+                    API.shows.collection_progress.method.as_str(),
+                    uri,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .await
+                .ok()?;
+            let progress = serde_json::from_value::<ShowProgress>(value).ok()?;
+            if progress.completed >= progress.aired {
+                return None;
+            }
+
+            let missing: Vec<MissingEpisode> = progress
+                .seasons
+                .iter()
+                .flat_map(|season| {
+                    season
+                        .episodes
+                        .iter()
+                        .filter(|e| !e.completed)
+                        .map(move |e| MissingEpisode { season: season.number, number: e.number })
+                })
+                .collect();
+
+            Some(MissingEpisodesItem {
+                show,
+                aired: progress.aired,
+                completed: progress.completed,
+                missing,
+            })
+        }));
+    }
+
+    let mut items = Vec::new();
+    for handle in handles {
+        if let Ok(Some(item)) = handle.await {
+            items.push(item);
+        }
+    }
+
+    // 缺得最多的排在前面
+    items.sort_by_key(|item| std::cmp::Reverse(item.missing.len()));
+    info!("Missing episodes for {} computed, {} shows incomplete", username, items.len());
+
+    Ok(items)
+}
+
 #[command]
 pub async fn get_up_next(
     app: AppHandle,
@@ -55,69 +176,75 @@ pub async fn get_up_next(
     limit: Option<usize>,
 ) -> Result<Vec<UpNextItem>, u16> {
     let current_page = page.unwrap_or(1);
-    let current_limit = limit.unwrap_or(20);
+    let current_limit = limit.unwrap_or(DEFAULT_UP_NEXT_LIMIT);
     let cache_key = format!("up_next_{}_p{}", username, current_page);
-    
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    // 尝试从缓存读取
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
-            if let Ok(items) = serde_json::from_value::<Vec<UpNextItem>>(result.data) {
-                cache_data = Some(items);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
 
-    // 如果有缓存数据
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            // 缓存新鲜，直接返回
-            info!("Up Next 缓存命中且新鲜，直接返回");
-            return Ok(data);
+    let items: Vec<UpNextItem> = cached_fetch(&app, &policy::UP_NEXT, CacheKey::Key(cache_key), move |app| async move {
+        fetch_up_next(&app, &username, current_page, current_limit).await
+    })
+    .await?;
+
+    let urls = items
+        .iter()
+        .filter_map(|item| item.show.images.poster.first().cloned())
+        .collect();
+    prefetch::prefetch_posters(&app, prefetch::UP_NEXT_GROUP, urls);
+    Ok(items)
+}
+
+/// 合并播放进度和 Up Next，同一集同时存在时只保留带进度的 resume 条目
+#[command]
+pub async fn get_continue_watching(
+    app: AppHandle,
+    username: String,
+    limit: Option<usize>,
+) -> Result<Vec<ContinueWatchingItem>, u16> {
+    // 播放进度需要登录，失败时只展示 Up Next
+    let playback = match get_playback_progress(app.clone(), None).await {
+        Ok(items) => items,
+        Err(e) => {
+            info!("获取播放进度失败 ({}), 仅返回 Up Next", e);
+            Vec::new()
         }
-        
-        // 缓存陈旧，先返回旧数据，后台刷新
-        info!("Up Next 缓存陈旧，返回旧数据并后台刷新");
-        let app_clone = app.clone();
-        let username_clone = username.clone();
-        let cache_key_clone = cache_key.clone();
-        
-        tokio::spawn(async move {
-            match fetch_up_next(&app_clone, &username_clone, current_page, current_limit).await {
-                Ok(new_data) => {
-                    info!("Up Next 后台刷新成功，共 {} 条", new_data.len());
-                    // 保存到缓存
-                    if let Some(pool) = app_clone.try_state::<DbPool>() {
-                        let json_data = serde_json::to_value(&new_data).unwrap_or_default();
-                        cache::set_user_data_cache(&pool.0, &cache_key_clone, &json_data).await;
-                    }
-                    // 通知前端更新
-                    let _ = app_clone.emit("user-data-update", serde_json::json!({
-                        "key": cache_key_clone,
-                        "data": new_data
-                    }));
-                },
-                Err(e) => error!("Up Next 后台刷新失败: {}", e),
-            }
-        });
-        
-        return Ok(data);
-    }
+    };
+    let up_next = get_up_next(app.clone(), username, Some(1), limit).await?;
 
-    // 无缓存，直接请求
-    info!("Up Next 无缓存，直接请求 API");
-    let result = fetch_up_next(&app, &username, current_page, current_limit).await?;
-    
-    // 保存到缓存
-    if let Some(pool) = app.try_state::<DbPool>() {
-        let json_data = serde_json::to_value(&result).unwrap_or_default();
-        cache::set_user_data_cache(&pool.0, &cache_key, &json_data).await;
-    }
-    
-    Ok(result)
+    let resumed_episodes: Vec<u32> = playback
+        .iter()
+        .filter_map(|p| p.episode.as_ref().map(|e| e.ids.trakt))
+        .collect();
+
+    let mut items: Vec<ContinueWatchingItem> = playback
+        .into_iter()
+        .map(|p| ContinueWatchingItem {
+            kind: "resume".to_string(),
+            sort_at: Some(p.paused_at.clone()),
+            playback: Some(p),
+            up_next: None,
+        })
+        .collect();
+
+    items.extend(
+        up_next
+            .into_iter()
+            .filter(|u| !resumed_episodes.contains(&u.next_episode.ids.trakt))
+            .map(|u| ContinueWatchingItem {
+                kind: "up_next".to_string(),
+                sort_at: u.progress.last_watched_at.clone(),
+                playback: None,
+                up_next: Some(u),
+            }),
+    );
+
+    items.sort_by(|a, b| {
+        let a_time = a.sort_at.as_deref().unwrap_or("");
+        let b_time = b.sort_at.as_deref().unwrap_or("");
+        b_time.cmp(a_time)
+    });
+    // 播放进度没有数量限制，合并后再截断
+    items.truncate(limit.unwrap_or(DEFAULT_UP_NEXT_LIMIT));
+
+    Ok(items)
 }
 
 /// 实际获取 Up Next 数据的内部函数
@@ -127,10 +254,8 @@ async fn fetch_up_next(
     page: usize,
     limit: usize,
 ) -> Result<Vec<UpNextItem>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-
     let watched_shows: Vec<WatchedShow> = {
-        let mut client = client.lock().await;
+        let mut client = api_client(app).await;
         let uri = format!("/users/{}/watched/shows", username);
 
         let result = client
@@ -149,6 +274,13 @@ async fn fetch_up_next(
         }
     };
 
+    // 过滤掉用户在进度中隐藏或放弃追的剧集
+    let hidden = hidden_ids(app, &["progress_watched", "dropped"]).await;
+    let watched_shows: Vec<WatchedShow> = watched_shows
+        .into_iter()
+        .filter(|w| !hidden.contains(&("show".to_string(), w.show.ids.trakt)))
+        .collect();
+
     let mut handles = Vec::new();
 
     // 扫描窗口：为了找到足够的 "未完成" 剧集，扫描 3 倍的数量
@@ -159,10 +291,7 @@ async fn fetch_up_next(
 
     for watched in page_items {
         let app_handle = app.clone();
-        let client_clone = {
-            let client_guard = client.lock().await;
-            client_guard.clone()
-        };
+        let client_clone = api_client(app).await;
 
         handles.push(tokio::spawn(async move {
             let mut client = client_clone;
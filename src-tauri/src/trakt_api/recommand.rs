@@ -1,32 +1,42 @@
 use crate::model::recommand::{MovieRecommand, ShowRecommand};
-use crate::trakt_api::ApiClient;
+use crate::trakt_api::hidden::hidden_ids;
+use crate::trakt_api::api_client;
 use crate::trakt_api::API;
+use crate::trakt_api::Entry;
+use std::collections::HashMap;
 use tauri::{command, AppHandle, Manager};
-use tokio::sync::Mutex;
 use crate::db::{DbPool, cache};
 use log::{info, error};
 
+// Trakt 推荐接口单次最多返回 100 条
+const MAX_RECOMMAND_LIMIT: u32 = 100;
+
 #[command]
-pub async fn movies_recommand(app: AppHandle) -> Result<Vec<MovieRecommand>, u16> {
-    let cache_key = "api_movies_recommand";
+pub async fn movies_recommand(
+    app: AppHandle,
+    ignore_collected: Option<bool>,
+    ignore_watchlisted: Option<bool>,
+    limit: Option<u32>,
+) -> Result<Vec<MovieRecommand>, u16> {
+    let limit = limit.unwrap_or(MAX_RECOMMAND_LIMIT).clamp(1, MAX_RECOMMAND_LIMIT);
+    let params = recommand_params(&API.recommand.movies, ignore_collected, ignore_watchlisted);
+    let cache_key = format!("api_movies_recommand_l{}{}", limit, params_key(&params));
 
     if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(json) = cache::get_api_response_cache(&pool.0, cache_key).await {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<MovieRecommand>>(json) {
-                return Ok(data);
+                return Ok(filter_hidden_movies(&app, data).await);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     if !client.authenticated {
         println!("movies_recommand: 用户未登录，返回 401");
         return Err(401);
     }
 
-    let params = API.recommand.movies.params.clone().unwrap();
     let result = client
         .req_api(
             &app,
@@ -34,7 +44,7 @@ pub async fn movies_recommand(app: AppHandle) -> Result<Vec<MovieRecommand>, u16
             API.recommand.movies.uri.clone(),
             Some(params),
             None,
-            Some(100),
+            Some(limit),
             None,
             true,
         )
@@ -46,9 +56,9 @@ pub async fn movies_recommand(app: AppHandle) -> Result<Vec<MovieRecommand>, u16
                 if let Some(pool) = app.try_state::<DbPool>() {
                     // 对于推荐列表，由于默认直接转成Value了，这里需要将Vec重新转回去存
                     let val = serde_json::to_value(&movies).unwrap();
-                    cache::set_api_response_cache(&pool.0, cache_key, &val).await;
+                    cache::set_api_response_cache(&pool.0, &cache_key, &val).await;
                 }
-                Ok(movies)
+                Ok(filter_hidden_movies(&app, movies).await)
             },
             Err(e) => {
                 println!("movies_recommand: 解析响应失败: {:?}", e);
@@ -60,26 +70,31 @@ pub async fn movies_recommand(app: AppHandle) -> Result<Vec<MovieRecommand>, u16
 }
 
 #[command]
-pub async fn shows_recommand(app: AppHandle) -> Result<Vec<ShowRecommand>, u16> {
-    let cache_key = "api_shows_recommand";
+pub async fn shows_recommand(
+    app: AppHandle,
+    ignore_collected: Option<bool>,
+    ignore_watchlisted: Option<bool>,
+    limit: Option<u32>,
+) -> Result<Vec<ShowRecommand>, u16> {
+    let limit = limit.unwrap_or(MAX_RECOMMAND_LIMIT).clamp(1, MAX_RECOMMAND_LIMIT);
+    let params = recommand_params(&API.recommand.shows, ignore_collected, ignore_watchlisted);
+    let cache_key = format!("api_shows_recommand_l{}{}", limit, params_key(&params));
 
     if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(json) = cache::get_api_response_cache(&pool.0, cache_key).await {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<ShowRecommand>>(json) {
-                return Ok(data);
+                return Ok(filter_hidden_shows(&app, data).await);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     if !client.authenticated {
         println!("shows_recommand: 用户未登录，返回 401");
         return Err(401);
     }
 
-    let params = API.recommand.shows.params.clone().unwrap();
     let result = client
         .req_api(
             &app,
@@ -87,7 +102,7 @@ pub async fn shows_recommand(app: AppHandle) -> Result<Vec<ShowRecommand>, u16>
             API.recommand.shows.uri.clone(),
             Some(params),
             None,
-            Some(100),
+            Some(limit),
             None,
             true,
         )
@@ -98,9 +113,9 @@ pub async fn shows_recommand(app: AppHandle) -> Result<Vec<ShowRecommand>, u16>
             Ok(shows) => {
                 if let Some(pool) = app.try_state::<DbPool>() {
                     let val = serde_json::to_value(&shows).unwrap();
-                    cache::set_api_response_cache(&pool.0, cache_key, &val).await;
+                    cache::set_api_response_cache(&pool.0, &cache_key, &val).await;
                 }
-                Ok(shows)
+                Ok(filter_hidden_shows(&app, shows).await)
             },
             Err(e) => {
                 println!("shows_recommand: 解析响应失败: {:?}", e);
@@ -116,26 +131,27 @@ pub async fn movies_recommand_page(
     app: AppHandle,
     page: u32,
     limit: u32,
+    ignore_collected: Option<bool>,
+    ignore_watchlisted: Option<bool>,
 ) -> Result<Vec<MovieRecommand>, u16> {
-    let cache_key = format!("api_movies_recommand_p{}_l{}", page, limit);
+    let params = recommand_params(&API.recommand.movies, ignore_collected, ignore_watchlisted);
+    let cache_key = format!("api_movies_recommand_p{}_l{}{}", page, limit, params_key(&params));
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<MovieRecommand>>(json) {
-                return Ok(data);
+                return Ok(filter_hidden_movies(&app, data).await);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     if !client.authenticated {
         println!("movies_recommand_page: 用户未登录，返回 401");
         return Err(401);
     }
 
-    let params = API.recommand.movies.params.clone().unwrap();
     let result = client
         .req_api(
             &app,
@@ -156,7 +172,7 @@ pub async fn movies_recommand_page(
                     let val = serde_json::to_value(&movies).unwrap();
                     cache::set_api_response_cache(&pool.0, &cache_key, &val).await;
                 }
-                Ok(movies)
+                Ok(filter_hidden_movies(&app, movies).await)
             },
             Err(e) => {
                 println!("movies_recommand_page: 解析响应失败: {:?}", e);
@@ -172,26 +188,27 @@ pub async fn shows_recommand_page(
     app: AppHandle,
     page: u32,
     limit: u32,
+    ignore_collected: Option<bool>,
+    ignore_watchlisted: Option<bool>,
 ) -> Result<Vec<ShowRecommand>, u16> {
-    let cache_key = format!("api_shows_recommand_p{}_l{}", page, limit);
+    let params = recommand_params(&API.recommand.shows, ignore_collected, ignore_watchlisted);
+    let cache_key = format!("api_shows_recommand_p{}_l{}{}", page, limit, params_key(&params));
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<ShowRecommand>>(json) {
-                return Ok(data);
+                return Ok(filter_hidden_shows(&app, data).await);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     if !client.authenticated {
         println!("shows_recommand_page: 用户未登录，返回 401");
         return Err(401);
     }
 
-    let params = API.recommand.shows.params.clone().unwrap();
     let result = client
         .req_api(
             &app,
@@ -212,7 +229,7 @@ pub async fn shows_recommand_page(
                     let val = serde_json::to_value(&shows).unwrap();
                     cache::set_api_response_cache(&pool.0, &cache_key, &val).await;
                 }
-                Ok(shows)
+                Ok(filter_hidden_shows(&app, shows).await)
             },
             Err(e) => {
                 println!("shows_recommand_page: 解析响应失败: {:?}", e);
@@ -222,3 +239,79 @@ pub async fn shows_recommand_page(
         Err(status) => Err(status),
     }
 }
+
+// 推荐结果中去掉用户在 recommendations 中隐藏的条目，过滤在读取时进行，缓存保留原始数据
+async fn filter_hidden_movies(app: &AppHandle, mut movies: Vec<MovieRecommand>) -> Vec<MovieRecommand> {
+    let hidden = hidden_ids(app, &["recommendations"]).await;
+    movies.retain(|m| !hidden.contains(&("movie".to_string(), m.ids.trakt)));
+    movies
+}
+
+async fn filter_hidden_shows(app: &AppHandle, mut shows: Vec<ShowRecommand>) -> Vec<ShowRecommand> {
+    let hidden = hidden_ids(app, &["recommendations"]).await;
+    shows.retain(|s| !hidden.contains(&("show".to_string(), s.ids.trakt)));
+    shows
+}
+
+/// 不再推荐某部电影/剧集：调用 Trakt 接口，并从已缓存的推荐页中移除
+#[command]
+pub async fn dismiss_recommendation(app: AppHandle, media_type: String, trakt_id: u32) -> Result<(), u16> {
+    let (entry, cache_prefix) = match media_type.as_str() {
+        "movie" | "movies" => (&API.recommand.dismiss_movie, "api_movies_recommand"),
+        "show" | "shows" => (&API.recommand.dismiss_show, "api_shows_recommand"),
+        _ => return Err(400),
+    };
+
+    {
+        let mut client = api_client(&app).await;
+        if !client.authenticated {
+            return Err(401);
+        }
+        let uri = entry.uri.replace("trakt_id", &trakt_id.to_string());
+        client
+            .req_api(&app, entry.method.as_str(), uri, None, None, None, None, false)
+            .await?;
+    }
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+        for key in cache::get_api_response_cache_keys(&pool.0, cache_prefix).await {
+            if let Some(serde_json::Value::Array(items)) = cache::get_api_response_cache(&pool.0, &key).await {
+                let remaining: Vec<serde_json::Value> = items
+                    .into_iter()
+                    .filter(|item| item["ids"]["trakt"].as_u64() != Some(trakt_id as u64))
+                    .collect();
+                cache::update_api_response_cache_data(&pool.0, &key, &serde_json::Value::Array(remaining)).await;
+            }
+        }
+        // Trakt 会把被移除的推荐加入 recommendations 隐藏列表
+        cache::delete_user_data_cache(&pool.0, "hidden_recommendations").await;
+    }
+
+    info!("Recommendation dismissed: {} {}", media_type, trakt_id);
+    Ok(())
+}
+
+// 以 api.json 中的参数为默认值，未传入的开关保持默认
+fn recommand_params(
+    entry: &Entry,
+    ignore_collected: Option<bool>,
+    ignore_watchlisted: Option<bool>,
+) -> HashMap<String, String> {
+    let mut params = entry.params.clone().unwrap_or_default();
+    if let Some(v) = ignore_collected {
+        params.insert("ignore_collected".to_string(), v.to_string());
+    }
+    if let Some(v) = ignore_watchlisted {
+        params.insert("ignore_watchlisted".to_string(), v.to_string());
+    }
+    params
+}
+
+fn params_key(params: &HashMap<String, String>) -> String {
+    let mut pairs: Vec<_> = params.iter().collect();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("_{}-{}", k, v))
+        .collect()
+}
@@ -1,27 +1,59 @@
 use crate::model::movie::Movie;
-use crate::model::shows::Show;
-use crate::trakt_api::ApiClient;
+use crate::model::people::Person;
+use crate::model::shows::{Episode, Show};
+use crate::model::user::TraktList;
+use crate::trakt_api::api_client;
 use crate::trakt_api::API;
-use serde::{Deserialize, Serialize};
+use log::{error, info};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
-use tauri::{command, AppHandle, Manager};
-use tokio::sync::Mutex;
+use tauri::{command, AppHandle};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SearchResult {
     #[serde(rename = "type")]
     pub result_type: String,
+    // ID 查询返回的 score 可能为 null，按 0 处理
+    #[serde(default, deserialize_with = "null_as_zero")]
     pub score: f64,
     pub movie: Option<Movie>,
     pub show: Option<Show>,
+    pub episode: Option<Episode>,
+    pub person: Option<Person>,
+    pub list: Option<TraktList>,
+}
+
+fn null_as_zero<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// 从用户输入中识别出的外部 ID
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalId {
+    pub id_type: String,
+    pub id: String,
+    pub media_type: Option<String>,
 }
 
 const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const MAX_SEARCH_LIMIT: u32 = 100;
+const SEARCH_TYPES: [&str; 5] = ["movie", "show", "episode", "person", "list"];
+const SEARCH_FIELDS: [&str; 9] = [
+    "title",
+    "tagline",
+    "overview",
+    "people",
+    "translations",
+    "aliases",
+    "name",
+    "biography",
+    "description",
+];
+const ID_TYPES: [&str; 4] = ["imdb", "tmdb", "tvdb", "trakt"];
 
 #[command]
 pub async fn search_media(app: AppHandle, query: String) -> Result<Vec<SearchResult>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     let mut params = HashMap::new();
     params.insert("query".to_string(), query);
@@ -46,3 +78,315 @@ pub async fn search_media(app: AppHandle, query: String) -> Result<Vec<SearchRes
         Err(result.unwrap_err())
     }
 }
+
+/// 高级搜索的可选条件，都为空时等同于普通搜索
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub types: Option<Vec<String>>,
+    pub fields: Option<Vec<String>>,
+    pub years: Option<String>,
+    pub genres: Option<String>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// 高级搜索：支持类型、字段、年份、类型过滤和分页
+/// 如果 query 是 IMDb/TMDB 链接或 `imdb:tt0000000` 形式，直接走 ID 查询
+#[command]
+pub async fn search_media_advanced(
+    app: AppHandle,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<Vec<SearchResult>, u16> {
+    let SearchOptions { types, fields, years, genres, page, limit } = options.unwrap_or_default();
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Err(400);
+    }
+
+    if let Some(external) = parse_external_id(&query) {
+        info!("搜索内容识别为外部 ID: {:?}", external);
+        return lookup_by_id(&app, &external.id_type, &external.id, external.media_type).await;
+    }
+
+    let types = join_allowed(types, &SEARCH_TYPES)?.unwrap_or_else(|| "movie,show".to_string());
+    let uri = API.search.typed.uri.replace("type", &types);
+
+    let mut params = API.search.typed.params.clone().unwrap_or_default();
+    params.insert("query".to_string(), query);
+    if let Some(f) = join_allowed(fields, &SEARCH_FIELDS)? {
+        params.insert("fields".to_string(), f);
+    }
+    if let Some(y) = years {
+        if !y.is_empty() { params.insert("years".to_string(), y); }
+    }
+    if let Some(g) = genres {
+        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+
+    let mut client = api_client(&app).await;
+    let result = client
+        .req_api(
+            &app,
+            API.search.typed.method.as_str(),
+            uri,
+            Some(params),
+            None,
+            Some(limit),
+            Some(page.unwrap_or(1)),
+            true,
+        )
+        .await;
+
+    match result {
+        Ok(value) => serde_json::from_value::<Vec<SearchResult>>(value).map_err(|e| {
+            error!("search_media_advanced: 解析响应失败: {:?}", e);
+            500
+        }),
+        Err(status) => Err(status),
+    }
+}
+
+/// 通过外部 ID 查询，id_type 为 imdb / tmdb / tvdb / trakt
+/// TMDB/TVDB 的 ID 在电影和剧集之间会重复，可通过 media_type 限定
+#[command]
+pub async fn search_by_id(
+    app: AppHandle,
+    id_type: String,
+    id: String,
+    media_type: Option<String>,
+) -> Result<Vec<SearchResult>, u16> {
+    lookup_by_id(&app, &id_type, &id, media_type).await
+}
+
+async fn lookup_by_id(
+    app: &AppHandle,
+    id_type: &str,
+    id: &str,
+    media_type: Option<String>,
+) -> Result<Vec<SearchResult>, u16> {
+    if !ID_TYPES.contains(&id_type) || id.is_empty() {
+        return Err(400);
+    }
+
+    let uri = API
+        .search
+        .id_lookup
+        .uri
+        .replace("id_type", id_type)
+        .replace("lookup_id", id);
+
+    let mut params = API.search.id_lookup.params.clone().unwrap_or_default();
+    if let Some(t) = media_type {
+        if !SEARCH_TYPES.contains(&t.as_str()) {
+            return Err(400);
+        }
+        params.insert("type".to_string(), t);
+    }
+
+    let mut client = api_client(app).await;
+    let result = client
+        .req_api(
+            app,
+            API.search.id_lookup.method.as_str(),
+            uri,
+            Some(params),
+            None,
+            None,
+            None,
+            true,
+        )
+        .await;
+
+    match result {
+        Ok(value) => serde_json::from_value::<Vec<SearchResult>>(value).map_err(|e| {
+            error!("search_by_id: 解析响应失败: {:?}", e);
+            500
+        }),
+        Err(status) => Err(status),
+    }
+}
+
+/// 校验并拼接逗号分隔的参数，出现不支持的值时返回 400
+fn join_allowed(values: Option<Vec<String>>, allowed: &[&str]) -> Result<Option<String>, u16> {
+    let values: Vec<String> = values
+        .unwrap_or_default()
+        .into_iter()
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect();
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+    if let Some(bad) = values.iter().find(|v| !allowed.contains(&v.as_str())) {
+        error!("不支持的搜索参数: {}", bad);
+        return Err(400);
+    }
+    Ok(Some(values.join(",")))
+}
+
+/// 识别 IMDb / TMDB / TVDB 链接，以及 `tt1234567`、`tmdb:123` 这类写法
+pub fn parse_external_id(input: &str) -> Option<ExternalId> {
+    let input = input.trim();
+    let lower = input.to_lowercase();
+
+    // imdb:tt0000000 / tmdb:123 / tvdb:123 / trakt:123
+    if let Some((prefix, rest)) = lower.split_once(':') {
+        let rest = rest.trim();
+        if ID_TYPES.contains(&prefix) && !rest.is_empty() && !rest.starts_with("//") {
+            let valid = if prefix == "imdb" { is_imdb_id(rest) } else { is_digits(rest) };
+            if valid {
+                return Some(ExternalId {
+                    id_type: prefix.to_string(),
+                    id: rest.to_string(),
+                    media_type: None,
+                });
+            }
+        }
+    }
+
+    if is_imdb_id(&lower) {
+        return Some(ExternalId {
+            id_type: "imdb".to_string(),
+            id: lower,
+            media_type: None,
+        });
+    }
+
+    // https://www.imdb.com/title/tt0111161/
+    if lower.contains("imdb.com/") {
+        let id = lower
+            .split(['/', '?', '#'])
+            .find(|s| is_imdb_id(s))?;
+        return Some(ExternalId {
+            id_type: "imdb".to_string(),
+            id: id.to_string(),
+            media_type: None,
+        });
+    }
+
+    // https://www.themoviedb.org/movie/550-fight-club, https://www.themoviedb.org/tv/1399
+    if lower.contains("themoviedb.org/") {
+        let segments: Vec<&str> = lower.split(['/', '?', '#']).collect();
+        for window in segments.windows(2) {
+            let media_type = match window[0] {
+                "movie" => "movie",
+                "tv" => "show",
+                "person" => "person",
+                _ => continue,
+            };
+            let id: String = window[1].chars().take_while(|c| c.is_ascii_digit()).collect();
+            if !id.is_empty() {
+                return Some(ExternalId {
+                    id_type: "tmdb".to_string(),
+                    id,
+                    media_type: Some(media_type.to_string()),
+                });
+            }
+        }
+    }
+
+    // https://thetvdb.com/?tab=series&id=81189
+    if lower.contains("thetvdb.com/") {
+        if let Ok(url) = url::Url::parse(&lower) {
+            if let Some((_, id)) = url.query_pairs().find(|(k, _)| k == "id") {
+                if is_digits(&id) {
+                    return Some(ExternalId {
+                        id_type: "tvdb".to_string(),
+                        id: id.to_string(),
+                        media_type: None,
+                    });
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn is_imdb_id(s: &str) -> bool {
+    s.len() >= 9 && s.starts_with("tt") && is_digits(&s[2..])
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_lookup_results_accept_null_score() {
+        // GET /search/imdb/tt0111161?type=movie 的返回
+        let payload = serde_json::json!([{
+            "type": "movie",
+            "score": null,
+            "movie": {
+                "title": "The Shawshank Redemption",
+                "year": 1994,
+                "ids": { "trakt": 234, "slug": "the-shawshank-redemption-1994", "imdb": "tt0111161", "tmdb": 278 }
+            }
+        }]);
+        let results = serde_json::from_value::<Vec<SearchResult>>(payload).unwrap();
+        assert_eq!(results[0].score, 0.0);
+        assert_eq!(results[0].movie.as_ref().map(|m| m.ids.trakt), Some(234));
+
+        let payload = serde_json::json!([{ "type": "show", "score": 42.5 }]);
+        let results = serde_json::from_value::<Vec<SearchResult>>(payload).unwrap();
+        assert_eq!(results[0].score, 42.5);
+    }
+
+    fn external(id_type: &str, id: &str, media_type: Option<&str>) -> Option<ExternalId> {
+        Some(ExternalId {
+            id_type: id_type.to_string(),
+            id: id.to_string(),
+            media_type: media_type.map(|t| t.to_string()),
+        })
+    }
+
+    #[test]
+    fn parses_prefixed_ids() {
+        assert_eq!(parse_external_id("imdb:tt0111161"), external("imdb", "tt0111161", None));
+        assert_eq!(parse_external_id(" TMDB:550 "), external("tmdb", "550", None));
+        assert_eq!(parse_external_id("trakt:12345"), external("trakt", "12345", None));
+        assert_eq!(parse_external_id("tmdb:abc"), None);
+        assert_eq!(parse_external_id("imdb:123"), None);
+    }
+
+    #[test]
+    fn parses_bare_imdb_id() {
+        assert_eq!(parse_external_id("tt0111161"), external("imdb", "tt0111161", None));
+        assert_eq!(parse_external_id("tt12"), None);
+    }
+
+    #[test]
+    fn parses_links() {
+        assert_eq!(
+            parse_external_id("https://www.imdb.com/title/tt0111161/?ref_=nv"),
+            external("imdb", "tt0111161", None)
+        );
+        assert_eq!(
+            parse_external_id("https://www.themoviedb.org/movie/550-fight-club"),
+            external("tmdb", "550", Some("movie"))
+        );
+        assert_eq!(
+            parse_external_id("https://www.themoviedb.org/tv/1399"),
+            external("tmdb", "1399", Some("show"))
+        );
+        assert_eq!(
+            parse_external_id("https://thetvdb.com/?tab=series&id=81189"),
+            external("tvdb", "81189", None)
+        );
+    }
+
+    #[test]
+    fn plain_text_is_not_an_id() {
+        assert_eq!(parse_external_id("fight club"), None);
+        assert_eq!(parse_external_id("star wars: a new hope"), None);
+    }
+}
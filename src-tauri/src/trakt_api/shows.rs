@@ -1,10 +1,15 @@
 use crate::model::shows::{
     Season, SeasonTranslations, Show, ShowAnticipated, ShowDetails, ShowTrending, ShowTranslations,
-    Episode, ShowWatched, ShowCollected,
+    Episode, ShowWatched, ShowCollected, ShowPlayed, ShowFavorited, ShowRecommended,
+    SeasonWithEpisodes, SeasonTree, SeasonNode, EpisodeNode,
 };
+use crate::model::discovery::DiscoveryFilter;
+use crate::trakt_api::cached_fetch::REFRESHING;
+use crate::trakt_api::progress::get_show_progress;
+use serde::{Deserialize, Serialize};
 
 use tauri::command;
-use crate::trakt_api::{ApiClient, API};
+use crate::trakt_api::{api_client, cached_chart_request, ApiClient, API};
 use tauri::{AppHandle, Manager, Emitter};
 use tokio::sync::Mutex;
 use std::collections::HashMap;
@@ -12,28 +17,28 @@ use crate::db::{DbPool, cache};
 use log::{info, error};
 
 #[command]
-pub async fn show_trending(app: AppHandle) -> Result<Vec<ShowTrending>, u16> {
-    let cache_key = "api_show_trending";
+pub async fn show_trending(app: AppHandle, filter: Option<DiscoveryFilter>) -> Result<Vec<ShowTrending>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_show_trending{}", filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(json) = cache::get_api_response_cache(&pool.0, cache_key).await {
+        if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
             if let Ok(data) = serde_json::from_value::<Vec<ShowTrending>>(json) {
                 return Ok(data);
             }
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let result = client
-        .req_api(&app, API.shows.trending.method.as_str(), API.shows.trending.uri.clone(), None, None, None, None, true)
+        .req_api(&app, API.shows.trending.method.as_str(), API.shows.trending.uri.clone(), Some(filter.to_params()), None, None, None, true)
         .await;
         
     match result {
         Ok(result) => {
             let show_trending = serde_json::from_value::<Vec<ShowTrending>>(result.clone()).unwrap();
             if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_api_response_cache(&pool.0, cache_key, &result).await;
+                cache::set_api_response_cache(&pool.0, &cache_key, &result).await;
             }
             Ok(show_trending)
         }
@@ -47,9 +52,11 @@ pub async fn show_trending_page(
     page: u32, 
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<ShowTrending>, u16> {
-    let cache_key = format!("api_show_trending_p{}_l{}_g{:?}_c{:?}", page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_show_trending_p{}_l{}{}", page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -59,16 +66,9 @@ pub async fn show_trending_page(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
     
     let result = client
         .req_api(&app, API.shows.trending.method.as_str(), API.shows.trending.uri.clone(), Some(params), None, Some(limit), Some(page), true)
@@ -92,9 +92,11 @@ pub async fn show_popular_page(
     page: u32, 
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<Show>, u16> {
-    let cache_key = format!("api_show_popular_p{}_l{}_g{:?}_c{:?}", page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_show_popular_p{}_l{}{}", page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -104,16 +106,9 @@ pub async fn show_popular_page(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
     
     let result = client
         .req_api(&app, API.shows.popular.method.as_str(), API.shows.popular.uri.clone(), Some(params), None, Some(limit), Some(page), true)
@@ -132,8 +127,14 @@ pub async fn show_popular_page(
 }
 
 #[command]
-pub async fn show_anticipated(app: AppHandle, page: u32, limit: u32) -> Result<Vec<ShowAnticipated>, u16> {
-    let cache_key = format!("api_show_anticipated_p{}_l{}", page, limit);
+pub async fn show_anticipated(
+    app: AppHandle,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<ShowAnticipated>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_show_anticipated_p{}_l{}{}", page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -143,10 +144,9 @@ pub async fn show_anticipated(app: AppHandle, page: u32, limit: u32) -> Result<V
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let result = client
-        .req_api(&app, API.shows.anticipated.method.as_str(), API.shows.anticipated.uri.clone(), None, None, Some(limit), Some(page), true)
+        .req_api(&app, API.shows.anticipated.method.as_str(), API.shows.anticipated.uri.clone(), Some(filter.to_params()), None, Some(limit), Some(page), true)
         .await;
         
     match result {
@@ -182,8 +182,13 @@ pub async fn show_details(app: AppHandle, id: u32) -> Result<ShowDetails, u16> {
         }
         
         // SWR
+        // 同一条数据已经在后台刷新时，不再重复请求
+        let Some(guard) = REFRESHING.try_begin(&format!("media:show_{}", id)) else {
+            return Ok(data);
+        };
         let app_clone = app.clone();
         tokio::spawn(async move {
+            let _guard = guard;
             match fetch_and_cache_show_details(&app_clone, id).await {
                 Ok(new_data) => {
                     info!("Background update success for show {}", id);
@@ -204,8 +209,7 @@ pub async fn show_details(app: AppHandle, id: u32) -> Result<ShowDetails, u16> {
 }
 
 async fn fetch_and_cache_show_details(app: &AppHandle, id: u32) -> Result<ShowDetails, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(app).await;
     let mut uri = API.shows.details.uri.clone();
     uri = uri.replace("id", &id.to_string());
     
@@ -219,7 +223,7 @@ async fn fetch_and_cache_show_details(app: &AppHandle, id: u32) -> Result<ShowDe
             let show_details = serde_json::from_value::<ShowDetails>(result.clone()).unwrap();
             
             if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_media_cache(&pool.0, "show", id, &result, cache::CACHE_TTL_SHORT).await;
+                cache::set_media_cache(&pool.0, "show", id, &result, cache::CACHE_TTL_LONG).await;
             }
             
             Ok(show_details)
@@ -248,8 +252,13 @@ pub async fn show_seasons(app: AppHandle, id: u32) -> Result<Vec<Season>, u16> {
             return Ok(data);
         }
         
+        // 同一条数据已经在后台刷新时，不再重复请求
+        let Some(guard) = REFRESHING.try_begin(&format!("media:show_seasons_{}", id)) else {
+            return Ok(data);
+        };
         let app_clone = app.clone();
         tokio::spawn(async move {
+            let _guard = guard;
             match fetch_and_cache_show_seasons(&app_clone, id).await {
                 Ok(new_data) => {
                     info!("Background update success for seasons of show {}", id);
@@ -270,8 +279,7 @@ pub async fn show_seasons(app: AppHandle, id: u32) -> Result<Vec<Season>, u16> {
 }
 
 async fn fetch_and_cache_show_seasons(app: &AppHandle, id: u32) -> Result<Vec<Season>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(app).await;
     let mut uri = API.shows.seasons.uri.clone();
     uri = uri.replace("id", &id.to_string());
     
@@ -284,7 +292,7 @@ async fn fetch_and_cache_show_seasons(app: &AppHandle, id: u32) -> Result<Vec<Se
         Ok(result) => {
             let seasons = serde_json::from_value::<Vec<Season>>(result.clone()).unwrap();
             if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_media_cache(&pool.0, "show_seasons", id, &result, cache::CACHE_TTL_SHORT).await;
+                cache::set_media_cache(&pool.0, "show_seasons", id, &result, cache::CACHE_TTL_LONG).await;
             }
             Ok(seasons)
         }
@@ -313,8 +321,13 @@ pub async fn get_season_episodes(app: AppHandle, id: u32, season: u32) -> Result
             return Ok(data);
         }
         
+        // 同一条数据已经在后台刷新时，不再重复请求
+        let Some(guard) = REFRESHING.try_begin(&format!("media:season_{}_{}", id, season)) else {
+            return Ok(data);
+        };
         let app_clone = app.clone();
         tokio::spawn(async move {
+            let _guard = guard;
             match fetch_and_cache_season_episodes(&app_clone, id, season).await {
                 Ok(new_data) => {
                     info!("Background update success for season {} of show {}", season, id);
@@ -336,8 +349,7 @@ pub async fn get_season_episodes(app: AppHandle, id: u32, season: u32) -> Result
 }
 
 async fn fetch_and_cache_season_episodes(app: &AppHandle, id: u32, season: u32) -> Result<Vec<Episode>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(app).await;
     let mut uri = API.shows.season_episodes.uri.clone();
     uri = uri.replace("id", &id.to_string()).replace("season_number", &season.to_string());
     
@@ -359,6 +371,109 @@ async fn fetch_and_cache_season_episodes(app: &AppHandle, id: u32, season: u32)
     }
 }
 
+// 季度树缓存：记录拉取时剧集的 updated_at，剧集更新后缓存即失效
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedSeasonTree {
+    updated_at: Option<String>,
+    seasons: Vec<SeasonWithEpisodes>,
+}
+
+/// 一次请求获取全部季度和剧集，并合并用户的观看进度
+/// 观看进度不进缓存，每次读取时合并，保证标记已看后立即生效
+#[command]
+pub async fn get_season_tree(app: AppHandle, id: u32) -> Result<SeasonTree, u16> {
+    // show_details 本身有缓存，这里用来判断季度树是否过期
+    let show_updated_at = show_details(app.clone(), id).await.ok().and_then(|d| d.updated_at);
+
+    let mut cache_data = None;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        if let Some(result) = cache::get_media_cache(&pool.0, "show_season_tree", id).await {
+            if let Ok(cached) = serde_json::from_value::<CachedSeasonTree>(result.data) {
+                let outdated = show_updated_at.is_some() && cached.updated_at != show_updated_at;
+                cache_data = Some((cached, result.is_stale || outdated));
+            }
+        }
+    }
+
+    let cached = match cache_data {
+        Some((cached, false)) => cached,
+        Some((cached, true)) => match fetch_and_cache_season_tree(&app, id, show_updated_at).await {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                error!("Refresh season tree of show {} failed: {}", id, e);
+                cached
+            }
+        },
+        None => fetch_and_cache_season_tree(&app, id, show_updated_at).await?,
+    };
+
+    let authenticated = {
+        let client = app.state::<Mutex<ApiClient>>();
+        let client = client.lock().await;
+        client.authenticated
+    };
+    let progress = if authenticated {
+        get_show_progress(app.clone(), id).await.ok()
+    } else {
+        None
+    };
+
+    let seasons = cached
+        .seasons
+        .into_iter()
+        .map(|s| {
+            let season_progress = progress
+                .as_ref()
+                .and_then(|p| p.seasons.iter().find(|sp| sp.number == s.season.number));
+            let episodes = s
+                .episodes
+                .into_iter()
+                .map(|episode| {
+                    let ep_progress = season_progress
+                        .and_then(|sp| sp.episodes.iter().find(|ep| ep.number == episode.number));
+                    EpisodeNode {
+                        episode,
+                        watched: ep_progress.is_some_and(|ep| ep.completed),
+                        last_watched_at: ep_progress.and_then(|ep| ep.last_watched_at.clone()),
+                    }
+                })
+                .collect::<Vec<_>>();
+            SeasonNode {
+                season: s.season,
+                completed: episodes.iter().filter(|e| e.watched).count() as u32,
+                episodes,
+            }
+        })
+        .collect();
+
+    Ok(SeasonTree {
+        show_id: id,
+        updated_at: cached.updated_at,
+        seasons,
+    })
+}
+
+async fn fetch_and_cache_season_tree(app: &AppHandle, id: u32, updated_at: Option<String>) -> Result<CachedSeasonTree, u16> {
+    let mut client = api_client(app).await;
+    let uri = API.shows.season_tree.uri.replace("id", &id.to_string());
+
+    let result = client
+        .req_api(app, API.shows.season_tree.method.as_str(), uri, API.shows.season_tree.params.clone(), None, None, None, false)
+        .await?;
+
+    let seasons = serde_json::from_value::<Vec<SeasonWithEpisodes>>(result).map_err(|e| {
+        error!("解析季度树失败: {:?}", e);
+        500u16
+    })?;
+    let cached = CachedSeasonTree { updated_at, seasons };
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+        let json_data = serde_json::to_value(&cached).unwrap_or_default();
+        cache::set_media_cache(&pool.0, "show_season_tree", id, &json_data, cache::CACHE_TTL_LONG).await;
+    }
+    Ok(cached)
+}
+
 #[command]
 pub async fn get_episode_details(app: AppHandle, id: u32, season: u32, episode: u32) -> Result<Episode, u16> {
     let mut cache_data = None;
@@ -379,8 +494,13 @@ pub async fn get_episode_details(app: AppHandle, id: u32, season: u32, episode:
             return Ok(data);
         }
         
+        // 同一条数据已经在后台刷新时，不再重复请求
+        let Some(guard) = REFRESHING.try_begin(&format!("media:episode_{}_{}_{}", id, season, episode)) else {
+            return Ok(data);
+        };
         let app_clone = app.clone();
         tokio::spawn(async move {
+            let _guard = guard;
             match fetch_and_cache_episode_details(&app_clone, id, season, episode).await {
                 Ok(new_data) => {
                     info!("Background update success for episode S{}E{} of show {}", season, episode, id);
@@ -403,8 +523,7 @@ pub async fn get_episode_details(app: AppHandle, id: u32, season: u32, episode:
 }
 
 async fn fetch_and_cache_episode_details(app: &AppHandle, id: u32, season: u32, episode: u32) -> Result<Episode, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(app).await;
     let mut uri = API.shows.episode_details.uri.clone();
     uri = uri
         .replace("id", &id.to_string())
@@ -432,8 +551,7 @@ async fn fetch_and_cache_episode_details(app: &AppHandle, id: u32, season: u32,
 
 #[command]
 pub async fn show_translation(app: AppHandle, id: u32, language: String) -> Result<ShowTranslations, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let mut uri = API.shows.trans.uri.clone();
     uri = uri.replace("id", &id.to_string()).replace("language", &language);
     let result = client.req_api(&app, API.shows.trans.method.as_str(), uri, None, None, None, None, true).await;
@@ -452,9 +570,11 @@ pub async fn show_watched_period(
     page: u32,
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<ShowWatched>, u16> {
-    let cache_key = format!("api_show_watched_{}_p{}_l{}_g{:?}_c{:?}", period, page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_show_watched_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -464,19 +584,12 @@ pub async fn show_watched_period(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
     let mut uri = API.shows.watched.uri.clone();
     uri = uri.replace("period", &period);
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
     
     let result = client
         .req_api(
@@ -512,9 +625,11 @@ pub async fn show_collected_period(
     page: u32,
     limit: u32,
     genres: Option<String>,
-    countries: Option<String>
+    countries: Option<String>,
+    filter: Option<DiscoveryFilter>
 ) -> Result<Vec<ShowCollected>, u16> {
-    let cache_key = format!("api_show_collected_{}_p{}_l{}_g{:?}_c{:?}", period, page, limit, genres, countries);
+    let filter = DiscoveryFilter::with_legacy(filter, genres, countries);
+    let cache_key = format!("api_show_collected_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
 
     if let Some(pool) = app.try_state::<DbPool>() {
         if let Some(json) = cache::get_api_response_cache(&pool.0, &cache_key).await {
@@ -524,19 +639,12 @@ pub async fn show_collected_period(
         }
     }
 
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     
     let mut uri = API.shows.collected.uri.clone();
     uri = uri.replace("period", &period);
     
-    let mut params = HashMap::new();
-    if let Some(g) = genres {
-        if !g.is_empty() && g != "all" { params.insert("genres".to_string(), g); }
-    }
-    if let Some(c) = countries {
-        if !c.is_empty() && c != "all" { params.insert("countries".to_string(), c); }
-    }
+    let params = filter.to_params();
     
     let result = client
         .req_api(
@@ -567,8 +675,7 @@ pub async fn show_collected_period(
 
 #[command]
 pub async fn season_trans(app: AppHandle, id: u32, season: u32, language: String) -> Result<SeasonTranslations, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let mut uri = API.shows.season_trans.uri.clone();
     // 修正: 占位符是 season_number
     uri = uri.replace("id", &id.to_string()).replace("season_number", &season.to_string()).replace("language", &language);
@@ -583,8 +690,7 @@ pub async fn season_trans(app: AppHandle, id: u32, season: u32, language: String
 }
 #[command]
 pub async fn episode_trans(app: AppHandle, id: u32, season: u32, episode: u32, language: String) -> Result<crate::model::shows::EpisodeTranslations, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
     let mut uri = API.shows.episode_trans.uri.clone();
     
     uri = uri.replace("id", &id.to_string())
@@ -600,3 +706,48 @@ pub async fn episode_trans(app: AppHandle, id: u32, season: u32, episode: u32, l
         Err(result.unwrap_err())
     }
 }
+
+#[command]
+pub async fn show_played_period(
+    app: AppHandle,
+    period: String,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<ShowPlayed>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_show_played_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
+    let uri = API.shows.played.uri.replace("period", &period);
+
+    cached_chart_request(&app, &API.shows.played, uri, &cache_key, filter.to_params(), Some(limit), Some(page)).await
+}
+
+#[command]
+pub async fn show_favorited_period(
+    app: AppHandle,
+    period: String,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<ShowFavorited>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_show_favorited_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
+    let uri = API.shows.favorited.uri.replace("period", &period);
+
+    cached_chart_request(&app, &API.shows.favorited, uri, &cache_key, filter.to_params(), Some(limit), Some(page)).await
+}
+
+#[command]
+pub async fn show_recommended_period(
+    app: AppHandle,
+    period: String,
+    page: u32,
+    limit: u32,
+    filter: Option<DiscoveryFilter>
+) -> Result<Vec<ShowRecommended>, u16> {
+    let filter = filter.unwrap_or_default();
+    let cache_key = format!("api_show_recommended_{}_p{}_l{}{}", period, page, limit, filter.cache_key());
+    let uri = API.shows.recommended.uri.replace("period", &period);
+
+    cached_chart_request(&app, &API.shows.recommended, uri, &cache_key, filter.to_params(), Some(limit), Some(page)).await
+}
@@ -0,0 +1,104 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// 合并并发的相同请求：同一个 key 同时只执行一次，其余调用者等待并共享结果
+pub struct SingleFlight<V: Clone> {
+    in_flight: Mutex<HashMap<String, broadcast::Sender<V>>>,
+}
+
+// 执行者被取消 (future 被丢弃) 时也要移除 key，否则等待者会一直挂起
+struct FlightGuard<'a, V: Clone> {
+    flights: &'a SingleFlight<V>,
+    key: String,
+}
+
+impl<V: Clone> Drop for FlightGuard<'_, V> {
+    fn drop(&mut self) {
+        if let Ok(mut map) = self.flights.in_flight.lock() {
+            map.remove(&self.key);
+        }
+    }
+}
+
+impl<V: Clone> SingleFlight<V> {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn run<F, Fut>(&self, key: String, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let waiting = {
+            let mut map = self.in_flight.lock().unwrap();
+            match map.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    let (sender, _) = broadcast::channel(1);
+                    map.insert(key.clone(), sender);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut receiver) = waiting {
+            if let Ok(value) = receiver.recv().await {
+                return value;
+            }
+            // 执行者被取消，自己执行一次
+            return f().await;
+        }
+
+        let guard = FlightGuard { flights: self, key };
+        let value = f().await;
+        let sender = self.in_flight.lock().unwrap().remove(&guard.key);
+        if let Some(sender) = sender {
+            let _ = sender.send(value.clone());
+        }
+        value
+    }
+}
+
+/// 记录正在执行的后台任务，同一个 key 同时只允许一个
+pub struct InFlightSet {
+    keys: Mutex<HashSet<String>>,
+}
+
+pub struct InFlightGuard<'a> {
+    set: &'a InFlightSet,
+    key: String,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut keys) = self.set.keys.lock() {
+            keys.remove(&self.key);
+        }
+    }
+}
+
+impl InFlightSet {
+    pub fn new() -> Self {
+        Self {
+            keys: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// 已有相同 key 在执行时返回 None
+    pub fn try_begin(&self, key: &str) -> Option<InFlightGuard<'_>> {
+        let mut keys = self.keys.lock().unwrap();
+        if keys.insert(key.to_string()) {
+            Some(InFlightGuard {
+                set: self,
+                key: key.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::db::cache::CacheKey;
+use crate::db::{cache, policy, DbPool};
+use crate::model::user::{FollowRequest, FollowResult, Follower, User, UserProfile};
+use crate::trakt_api::cached_fetch::cached_fetch;
+use crate::trakt_api::user::HistoryItem;
+use crate::trakt_api::{api_client, Entry, API};
+use log::{error, info};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tauri::{command, AppHandle, Manager};
+use tokio::sync::Semaphore;
+
+/// 好友动态：好友公开观看记录中的一条
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FriendActivity {
+    pub user: User,
+    pub history: HistoryItem,
+}
+
+// 拉取好友观看记录时的最大并发数
+const FRIENDS_ACTIVITY_CONCURRENCY: usize = 4;
+const FRIENDS_ACTIVITY_PER_USER: u32 = 10;
+
+#[command]
+pub async fn get_public_profile(app: AppHandle, id: String) -> Result<User, u16> {
+    let uri = API.social.profile.uri.replace("id", &id);
+    // 与 get_user_profile 的 user_profile_me (UserProfile) 区分开
+    get_cached(&app, &API.social.profile, uri, format!("public_profile_{}", id)).await
+}
+
+#[command]
+pub async fn get_followers(app: AppHandle, id: String) -> Result<Vec<Follower>, u16> {
+    let uri = API.social.followers.uri.replace("id", &id);
+    get_cached(&app, &API.social.followers, uri, format!("followers_{}", id)).await
+}
+
+#[command]
+pub async fn get_following(app: AppHandle, id: String) -> Result<Vec<Follower>, u16> {
+    let uri = API.social.following.uri.replace("id", &id);
+    get_cached(&app, &API.social.following, uri, format!("following_{}", id)).await
+}
+
+#[command]
+pub async fn get_friends(app: AppHandle, id: String) -> Result<Vec<Follower>, u16> {
+    let uri = API.social.friends.uri.replace("id", &id);
+    get_cached(&app, &API.social.friends, uri, format!("friends_{}", id)).await
+}
+
+#[command]
+pub async fn get_follow_requests(app: AppHandle) -> Result<Vec<FollowRequest>, u16> {
+    get_cached(&app, &API.social.requests, API.social.requests.uri.clone(), "follow_requests".to_string()).await
+}
+
+#[command]
+pub async fn follow_user(app: AppHandle, id: String) -> Result<FollowResult, u16> {
+    let uri = API.social.follow.uri.replace("id", &id);
+    let result = send(&app, &API.social.follow, uri).await?;
+    invalidate_relations(&app, &id).await;
+    serde_json::from_value::<FollowResult>(result).map_err(|e| {
+        error!("解析 follow 响应失败: {:?}", e);
+        500
+    })
+}
+
+#[command]
+pub async fn unfollow_user(app: AppHandle, id: String) -> Result<(), u16> {
+    let uri = API.social.unfollow.uri.replace("id", &id);
+    send(&app, &API.social.unfollow, uri).await?;
+    invalidate_relations(&app, &id).await;
+    Ok(())
+}
+
+#[command]
+pub async fn approve_follow_request(app: AppHandle, request_id: u64) -> Result<Follower, u16> {
+    let uri = API.social.approve_request.uri.replace("request_id", &request_id.to_string());
+    let result = send(&app, &API.social.approve_request, uri).await?;
+    let follower = serde_json::from_value::<Follower>(result).map_err(|e| {
+        error!("解析 approve 响应失败: {:?}", e);
+        500u16
+    })?;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        cache::delete_user_data_cache(&pool.0, "follow_requests").await;
+    }
+    invalidate_relations(&app, &follower.user.ids.slug).await;
+    Ok(follower)
+}
+
+#[command]
+pub async fn deny_follow_request(app: AppHandle, request_id: u64) -> Result<(), u16> {
+    let uri = API.social.deny_request.uri.replace("request_id", &request_id.to_string());
+    send(&app, &API.social.deny_request, uri).await?;
+    if let Some(pool) = app.try_state::<DbPool>() {
+        cache::delete_user_data_cache(&pool.0, "follow_requests").await;
+    }
+    Ok(())
+}
+
+/// 好友动态：并发拉取每位好友最近的公开观看记录，按观看时间倒序合并
+#[command]
+pub async fn get_friends_activity(
+    app: AppHandle,
+    id: String,
+    limit: Option<usize>,
+) -> Result<Vec<FriendActivity>, u16> {
+    let cache_key = format!("friends_activity_{}", id);
+    let limit = limit.unwrap_or(50);
+    let items = cached_fetch(&app, &policy::FRIENDS_ACTIVITY, CacheKey::Key(cache_key), move |app| async move {
+        fetch_friends_activity(&app, &id).await
+    })
+    .await?;
+    Ok(items.into_iter().take(limit).collect())
+}
+
+async fn fetch_friends_activity(app: &AppHandle, id: &str) -> Result<Vec<FriendActivity>, u16> {
+    let friends = get_friends(app.clone(), id.to_string()).await?;
+
+    let semaphore = Arc::new(Semaphore::new(FRIENDS_ACTIVITY_CONCURRENCY));
+    let mut handles = Vec::new();
+
+    for friend in friends {
+        // 私密账号的观看记录不可见
+        if friend.user.private {
+            continue;
+        }
+        let app_handle = app.clone();
+        let semaphore = semaphore.clone();
+        let client_clone = api_client(app).await;
+
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.ok()?;
+            let mut client = client_clone;
+            let uri = API.user.history.uri.replace("id", &friend.user.ids.slug);
+            let result = client
+                .req_api(
+                    &app_handle,
+                    API.user.history.method.as_str(),
+                    uri,
+                    None,
+                    None,
+                    Some(FRIENDS_ACTIVITY_PER_USER),
+                    Some(1),
+                    true,
+                )
+                .await
+                .ok()?;
+            let history = serde_json::from_value::<Vec<HistoryItem>>(result).ok()?;
+            Some(
+                history
+                    .into_iter()
+                    .map(|h| FriendActivity { user: friend.user.clone(), history: h })
+                    .collect::<Vec<_>>(),
+            )
+        }));
+    }
+
+    let mut activity = Vec::new();
+    for handle in handles {
+        if let Ok(Some(items)) = handle.await {
+            activity.extend(items);
+        }
+    }
+    activity.sort_by(|a, b| b.history.watched_at.cmp(&a.history.watched_at));
+    info!("Friends activity for {} refreshed, {} items", id, activity.len());
+    Ok(activity)
+}
+
+// 关注关系变化只影响对方和自己：删除双方的 followers/following/friends 缓存，
+// 自己的按 me 和用户名两种 key 都删，另外删除对方的公开资料 (关注数变化)
+async fn invalidate_relations(app: &AppHandle, id: &str) {
+    if let Some(pool) = app.try_state::<DbPool>() {
+        let mut users = own_ids(&pool.0).await;
+        users.push(id.to_string());
+        for user in &users {
+            for prefix in ["followers", "following", "friends"] {
+                cache::delete_user_data_cache(&pool.0, &format!("{}_{}", prefix, user)).await;
+            }
+        }
+        cache::delete_user_data_cache(&pool.0, &format!("public_profile_{}", id)).await;
+    }
+}
+
+// 当前用户在缓存 key 中可能出现的 id：me 以及缓存的个人资料中的 slug
+async fn own_ids(pool: &sqlx::SqlitePool) -> Vec<String> {
+    let mut ids = vec!["me".to_string()];
+    if let Some(result) = cache::get_user_data_cache(pool, "user_profile_me").await {
+        if let Ok(profile) = serde_json::from_value::<UserProfile>(result.data) {
+            ids.push(profile.user.ids.slug);
+        }
+    }
+    ids
+}
+
+async fn send(app: &AppHandle, entry: &Entry, uri: String) -> Result<Value, u16> {
+    let mut client = api_client(app).await;
+    if !client.authenticated {
+        return Err(401);
+    }
+    client
+        .req_api(app, entry.method.as_str(), uri, None, None, None, None, false)
+        .await
+}
+
+// 社交数据按 SOCIAL 策略缓存在 user_data_cache
+async fn get_cached<T>(app: &AppHandle, entry: &'static Entry, uri: String, cache_key: String) -> Result<T, u16>
+where
+    T: DeserializeOwned + Serialize + Send + 'static,
+{
+    cached_fetch(app, &policy::SOCIAL, CacheKey::Key(cache_key), move |app| async move {
+        let mut client = api_client(&app).await;
+        let result = client
+            .req_api(&app, entry.method.as_str(), uri, entry.params.clone(), None, None, None, false)
+            .await?;
+        serde_json::from_value::<T>(result).map_err(|e| {
+            error!("解析 {} 失败: {:?}", entry.uri, e);
+            500
+        })
+    })
+    .await
+}
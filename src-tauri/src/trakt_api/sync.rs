@@ -1,9 +1,14 @@
-use crate::trakt_api::ApiClient;
+use crate::db::cache::CacheKey;
+use crate::db::{cache, policy, DbPool};
+use crate::model::movie::Movie;
+use crate::model::shows::{Episode, Show};
+use crate::trakt_api::api_client;
+use crate::trakt_api::cached_fetch::cached_fetch;
 use crate::trakt_api::API;
+use log::{error, info};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tauri::{command, AppHandle, Manager};
-use tokio::sync::Mutex;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SyncResponse {
@@ -48,14 +53,28 @@ pub struct ItemIds {
     pub slug: Option<String>,
 }
 
+/// 暂停中的播放进度 (/sync/playback)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlaybackItem {
+    pub id: u64,
+    pub progress: f32,
+    pub paused_at: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub movie: Option<Movie>,
+    pub episode: Option<Episode>,
+    pub show: Option<Show>,
+}
+
+const PLAYBACK_TYPES: [&str; 3] = ["all", "movies", "episodes"];
+
 #[command]
 pub async fn add_to_collection(
     app: AppHandle,
     media_type: String,
     trakt_id: u32,
 ) -> Result<SyncResponse, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     let body = if media_type == "movie" {
         json!({
@@ -116,8 +135,7 @@ pub async fn remove_from_collection(
     media_type: String,
     trakt_id: u32,
 ) -> Result<SyncResponse, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     let body = if media_type == "movie" {
         json!({
@@ -178,8 +196,7 @@ pub async fn add_to_watchlist(
     media_type: String,
     trakt_id: u32,
 ) -> Result<SyncResponse, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     let body = if media_type == "movie" {
         json!({
@@ -240,8 +257,7 @@ pub async fn remove_from_watchlist(
     media_type: String,
     trakt_id: u32,
 ) -> Result<SyncResponse, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     let body = if media_type == "movie" {
         json!({
@@ -302,8 +318,7 @@ pub async fn mark_as_watched(
     media_type: String,
     trakt_id: u32,
 ) -> Result<SyncResponse, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(&app).await;
 
     let body = if media_type == "movie" {
         json!({
@@ -361,9 +376,102 @@ pub async fn mark_as_watched(
         .await;
 
     if let Ok(result) = result {
+        // 新增观看记录后，所有分页和筛选的观看记录缓存都已过时
+        if let Some(pool) = app.try_state::<DbPool>() {
+            cache::delete_user_data_cache_prefix(&pool.0, "history_").await;
+        }
         let response = serde_json::from_value::<SyncResponse>(result).unwrap();
         Ok(response)
     } else {
         Err(result.unwrap_err())
     }
 }
+
+/// 获取未看完的电影/剧集播放进度，media_type 为 movies / episodes，默认全部
+#[command]
+pub async fn get_playback_progress(
+    app: AppHandle,
+    media_type: Option<String>,
+) -> Result<Vec<PlaybackItem>, u16> {
+    let media_type = media_type.unwrap_or_else(|| "all".to_string());
+    if !PLAYBACK_TYPES.contains(&media_type.as_str()) {
+        return Err(400);
+    }
+    let cache_key = format!("playback_{}", media_type);
+    cached_fetch(&app, &policy::PLAYBACK, CacheKey::Key(cache_key), move |app| async move {
+        fetch_playback(&app, &media_type).await
+    })
+    .await
+}
+
+async fn fetch_playback(app: &AppHandle, media_type: &str) -> Result<Vec<PlaybackItem>, u16> {
+    let mut client = api_client(app).await;
+
+    if !client.authenticated {
+        return Err(401);
+    }
+
+    let uri = if media_type == "all" {
+        API.sync.playback.uri.replace("/type", "")
+    } else {
+        API.sync.playback.uri.replace("type", media_type)
+    };
+
+    let result = client
+        .req_api(
+            app,
+            API.sync.playback.method.as_str(),
+            uri,
+            API.sync.playback.params.clone(),
+            None,
+            None,
+            None,
+            true,
+        )
+        .await?;
+
+    serde_json::from_value::<Vec<PlaybackItem>>(result).map_err(|e| {
+        error!("解析 playback 失败: {:?}", e);
+        500
+    })
+}
+
+/// 删除一条播放进度，同时从本地缓存中移除
+#[command]
+pub async fn remove_playback(app: AppHandle, playback_id: u64) -> Result<(), u16> {
+    {
+        let mut client = api_client(&app).await;
+        let uri = API
+            .sync
+            .remove_playback
+            .uri
+            .replace("playback_id", &playback_id.to_string());
+        client
+            .req_api(
+                &app,
+                API.sync.remove_playback.method.as_str(),
+                uri,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await?;
+    }
+
+    if let Some(pool) = app.try_state::<DbPool>() {
+        for media_type in PLAYBACK_TYPES {
+            let cache_key = format!("playback_{}", media_type);
+            if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
+                if let Ok(mut items) = serde_json::from_value::<Vec<PlaybackItem>>(result.data) {
+                    items.retain(|item| item.id != playback_id);
+                    let json_data = serde_json::to_value(&items).unwrap_or_default();
+                    cache::set_user_data_cache(&pool.0, &cache_key, &json_data).await;
+                }
+            }
+        }
+    }
+    info!("Playback {} removed", playback_id);
+    Ok(())
+}
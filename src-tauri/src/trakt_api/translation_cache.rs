@@ -1,123 +1,265 @@
-use tauri::{command, AppHandle, Manager};
-use log::debug;
-use crate::model::translation::TranslationData;
-use crate::model::movie::MovieTranslations;
-use crate::model::shows::{ShowTranslations, SeasonTranslations, EpisodeTranslations};
+use tauri::{command, AppHandle, Emitter, Manager};
+use log::{debug, error};
+use lazy_static::lazy_static;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use tokio::sync::Semaphore;
+use crate::model::translation::{TranslationBatchItem, TranslationData, TranslationTarget};
+use crate::model::movie::MovieTranslation;
+use crate::model::shows::{ShowTranslation, SeasonTranslation, EpisodeTranslation};
 use crate::db::{DbPool, cache};
+use crate::app_conf::{default_translation_fallbacks, default_translation_language};
+use crate::trakt_api::single_flight::InFlightSet;
+use crate::trakt_api::{api_client, ApiClient, Entry, API};
+
+const TRANSLATION_UPDATE_EVENT: &str = "translation-update";
+const PREFETCH_CONCURRENCY: usize = 4;
+
+lazy_static! {
+    // 所有批次共享并发上限
+    static ref PREFETCH_LIMIT: Semaphore = Semaphore::new(PREFETCH_CONCURRENCY);
+    // 同一条翻译正在预取时，重复的请求直接跳过，结果由先发起的任务通过事件返回
+    static ref PREFETCHING: InFlightSet = InFlightSet::new();
+}
 
 #[command]
 pub async fn get_movie_translation_cached(app: AppHandle, id: u32) -> Result<Option<TranslationData>, u16> {
-    // 1. 检查 DB 缓存
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(data) = cache::get_translation_cache(&pool.0, "movie", id).await {
-            if let Ok(translation) = serde_json::from_value::<TranslationData>(data) {
-                return Ok(Some(translation));
-            }
-        }
-    }
-    
-    // 2. 缓存未命中，调用 API
-    match super::movie::movie_translation(app.clone(), id, "zh".to_string()).await {
-        Ok(translations) => {
-            let translation_data = process_movie_translations(translations);
-            
-            // 3. 保存到缓存
-            if let Some(data) = &translation_data {
-                if let Some(pool) = app.try_state::<DbPool>() {
-                    cache::set_translation_cache(&pool.0, "movie", id, &serde_json::to_value(data).unwrap()).await;
-                }
-            }
-            
-            Ok(translation_data)
-        }
-        Err(code) => Err(code)
-    }
+    cached_translation(&app, "movie", id, |language| {
+        super::movie::movie_translation(app.clone(), id, language)
+    })
+    .await
 }
 
 #[command]
 pub async fn get_show_translation_cached(app: AppHandle, id: u32) -> Result<Option<TranslationData>, u16> {
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(data) = cache::get_translation_cache(&pool.0, "show", id).await {
-            if let Ok(translation) = serde_json::from_value::<TranslationData>(data) {
-                return Ok(Some(translation));
-            }
-        }
-    }
-    
-    match super::shows::show_translation(app.clone(), id, "zh".to_string()).await {
-        Ok(translations) => {
-            let translation_data = process_show_translations(translations);
-            
-            if let Some(data) = &translation_data {
-                if let Some(pool) = app.try_state::<DbPool>() {
-                    cache::set_translation_cache(&pool.0, "show", id, &serde_json::to_value(data).unwrap()).await;
-                }
-            }
-            
-            Ok(translation_data)
-        }
-        Err(code) => Err(code)
-    }
+    cached_translation(&app, "show", id, |language| {
+        super::shows::show_translation(app.clone(), id, language)
+    })
+    .await
 }
 
 #[command]
 pub async fn get_season_translation_cached(app: AppHandle, show_id: u32, season: u32) -> Result<Option<TranslationData>, u16> {
-    // 使用 show_id + season 作为唯一标识
-    // 为了复用 cache::get_translation_cache (id: u32)，我们需要一种映射方式
-    // 这里简单地构造一个 composite key (show_id), 但 media_type 为 "season_{season}"
+    // 复用 cache::get_translation_cache (id: u32)，以 show_id 为 id，media_type 为 "season_{season}"
     let type_key = format!("season_{}", season);
-    
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(data) = cache::get_translation_cache(&pool.0, &type_key, show_id).await {
-            if let Ok(translation) = serde_json::from_value::<TranslationData>(data) {
-                return Ok(Some(translation));
-            }
-        }
-    }
-    
-    match super::shows::season_trans(app.clone(), show_id, season, "zh".to_string()).await {
-        Ok(translations) => {
-            let translation_data = process_season_translations(translations);
-            
-            if let Some(data) = &translation_data {
-                if let Some(pool) = app.try_state::<DbPool>() {
-                    cache::set_translation_cache(&pool.0, &type_key, show_id, &serde_json::to_value(data).unwrap()).await;
-                }
-            }
-            
-            Ok(translation_data)
-        }
-        Err(code) => Err(code)
-    }
+    cached_translation(&app, &type_key, show_id, |language| {
+        super::shows::season_trans(app.clone(), show_id, season, language)
+    })
+    .await
 }
 
 
 #[command]
 pub async fn get_episode_translation_cached(app: AppHandle, show_id: u32, season: u32, episode: u32) -> Result<Option<TranslationData>, u16> {
     let type_key = format!("episode_{}_{}", season, episode);
-    
+    cached_translation(&app, &type_key, show_id, |language| {
+        super::shows::episode_trans(app.clone(), show_id, season, episode, language)
+    })
+    .await
+}
+
+/// 设置中的首选语言加上回退顺序，去重，遇到 original 为止
+pub(crate) async fn translation_chain(app: &AppHandle) -> Vec<String> {
+    let (language, fallbacks) = match crate::settings::get_app_config(app.clone()).await {
+        Ok(conf) => (conf.translation_language, conf.translation_fallbacks),
+        Err(_) => (default_translation_language(), default_translation_fallbacks()),
+    };
+    let mut chain: Vec<String> = Vec::new();
+    for tag in std::iter::once(language).chain(fallbacks) {
+        if chain.contains(&tag) {
+            continue;
+        }
+        let is_original = tag == ORIGINAL;
+        chain.push(tag);
+        if is_original {
+            break;
+        }
+    }
+    chain
+}
+
+// 先查缓存 (key 中带语言链，修改语言设置后不会命中旧语言的翻译)，未命中时按语言链请求
+async fn cached_translation<T, F, Fut>(app: &AppHandle, type_key: &str, id: u32, fetch: F) -> Result<Option<TranslationData>, u16>
+where
+    T: TranslationEntry,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, u16>>,
+{
+    let chain = translation_chain(app).await;
+    let cache_type = cache_type_key(type_key, &chain);
+
+    if let Some(translation) = read_cached_translation(app, &cache_type, id).await {
+        return Ok(translation);
+    }
+
+    fetch_and_store_translation(app, &chain, &cache_type, id, fetch).await
+}
+
+fn cache_type_key(type_key: &str, chain: &[String]) -> String {
+    format!("{}_{}", type_key, chain.join(","))
+}
+
+// 外层为是否命中缓存，内层 None 表示已经确认没有可用的翻译
+async fn read_cached_translation(app: &AppHandle, cache_type: &str, id: u32) -> Option<Option<TranslationData>> {
+    let pool = app.try_state::<DbPool>()?;
+    let data = cache::get_translation_cache(&pool.0, cache_type, id).await?;
+    let data = serde_json::from_value::<TranslationData>(data).ok()?;
+    Some(if is_empty_translation(&data) { None } else { Some(data) })
+}
+
+async fn fetch_and_store_translation<T, F, Fut>(
+    app: &AppHandle,
+    chain: &[String],
+    cache_type: &str,
+    id: u32,
+    fetch: F,
+) -> Result<Option<TranslationData>, u16>
+where
+    T: TranslationEntry,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, u16>>,
+{
+    let translation_data = resolve_translation(chain, fetch).await?;
+
+    // 没有翻译时也缓存一条空记录，避免每次打开都按语言链重新请求
     if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(data) = cache::get_translation_cache(&pool.0, &type_key, show_id).await {
-            if let Ok(translation) = serde_json::from_value::<TranslationData>(data) {
-                return Ok(Some(translation));
-            }
+        let data = translation_data.clone().unwrap_or_else(|| TranslationData {
+            title: None,
+            overview: None,
+            tagline: None,
+            updated_at: cache::get_timestamp() as u64,
+        });
+        cache::set_translation_cache(&pool.0, cache_type, id, &serde_json::to_value(&data).unwrap()).await;
+    }
+
+    Ok(translation_data)
+}
+
+/// 列表页批量获取翻译：已缓存的直接返回，未缓存的在后台限流请求，
+/// 每完成一项发出 translation-update 事件 (格式同 TranslationBatchItem)
+#[command]
+pub async fn prefetch_translations(app: AppHandle, items: Vec<TranslationTarget>) -> Result<Vec<TranslationBatchItem>, u16> {
+    if items.iter().any(|item| item.media_type != "movie" && item.media_type != "show") {
+        return Err(400);
+    }
+
+    let chain = translation_chain(&app).await;
+    let mut cached = Vec::new();
+    let mut missing = Vec::new();
+    let mut seen = HashSet::new();
+
+    for item in items {
+        if !seen.insert(item.clone()) {
+            continue;
+        }
+        let cache_type = cache_type_key(&item.media_type, &chain);
+        match read_cached_translation(&app, &cache_type, item.id).await {
+            Some(data) => cached.push(TranslationBatchItem {
+                media_type: item.media_type,
+                id: item.id,
+                data,
+            }),
+            None => missing.push(item),
         }
     }
-    
-    match super::shows::episode_trans(app.clone(), show_id, season, episode, "zh".to_string()).await {
-        Ok(translations) => {
-            let translation_data = process_episode_translations(translations);
-            
-            if let Some(data) = &translation_data {
-                if let Some(pool) = app.try_state::<DbPool>() {
-                    cache::set_translation_cache(&pool.0, &type_key, show_id, &serde_json::to_value(data).unwrap()).await;
+
+    debug!("Translation prefetch: {} cached, {} missing", cached.len(), missing.len());
+    if missing.is_empty() {
+        return Ok(cached);
+    }
+
+    // 后台任务并发请求，使用独立的客户端副本，避免一直占着全局锁
+    let client_clone = api_client(&app).await;
+
+    for item in missing {
+        let flight_key = format!("{}_{}", cache_type_key(&item.media_type, &chain), item.id);
+        let guard = match PREFETCHING.try_begin(&flight_key) {
+            Some(guard) => guard,
+            None => continue,
+        };
+        let app = app.clone();
+        let chain = chain.clone();
+        let client = client_clone.clone();
+
+        tokio::spawn(async move {
+            let _guard = guard;
+            let _permit = match PREFETCH_LIMIT.acquire().await {
+                Ok(permit) => permit,
+                Err(_) => return,
+            };
+
+            let cache_type = cache_type_key(&item.media_type, &chain);
+            let result = match item.media_type.as_str() {
+                "movie" => {
+                    fetch_and_store_translation(&app, &chain, &cache_type, item.id, |language| {
+                        request_translations::<MovieTranslation>(app.clone(), client.clone(), &API.movie.trans, item.id, language)
+                    })
+                    .await
+                }
+                _ => {
+                    fetch_and_store_translation(&app, &chain, &cache_type, item.id, |language| {
+                        request_translations::<ShowTranslation>(app.clone(), client.clone(), &API.shows.trans, item.id, language)
+                    })
+                    .await
                 }
+            };
+
+            match result {
+                Ok(data) => {
+                    let _ = app.emit(TRANSLATION_UPDATE_EVENT, TranslationBatchItem {
+                        media_type: item.media_type,
+                        id: item.id,
+                        data,
+                    });
+                }
+                Err(code) => error!("预取翻译失败 {}: {}", flight_key, code),
             }
-            
-            Ok(translation_data)
+        });
+    }
+
+    Ok(cached)
+}
+
+async fn request_translations<T: DeserializeOwned>(
+    app: AppHandle,
+    mut client: ApiClient,
+    entry: &Entry,
+    id: u32,
+    language: String,
+) -> Result<Vec<T>, u16> {
+    let uri = entry.uri.replace("id", &id.to_string()).replace("language", &language);
+    let value = client.req_api(&app, entry.method.as_str(), uri, None, None, None, None, true).await?;
+    serde_json::from_value::<Vec<T>>(value).map_err(|e| {
+        error!("解析翻译失败: {:?}", e);
+        500
+    })
+}
+
+// 按语言链依次匹配，同一语言只请求一次 (接口按语言返回所有地区的翻译)
+async fn resolve_translation<T, F, Fut>(chain: &[String], fetch: F) -> Result<Option<TranslationData>, u16>
+where
+    T: TranslationEntry,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, u16>>,
+{
+    let mut fetched: HashMap<String, Vec<T>> = HashMap::new();
+
+    for tag in chain {
+        let Some((language, country)) = split_tag(tag) else {
+            debug!("Translation chain reached original");
+            return Ok(None);
+        };
+        if !fetched.contains_key(&language) {
+            let translations = fetch(language.clone()).await?;
+            fetched.insert(language.clone(), translations);
+        }
+        if let Some(data) = process_translations(&fetched[&language], country.as_deref()) {
+            return Ok(Some(data));
         }
-        Err(code) => Err(code)
     }
+
+    Ok(None)
 }
 
 #[command]
@@ -160,79 +302,66 @@ pub async fn get_translation_cache_stats(app: AppHandle) -> Result<(u32, u32), S
     }
 }
 
-fn process_movie_translations(translations: MovieTranslations) -> Option<TranslationData> {
-    if translations.is_empty() {
-        return None;
+const ORIGINAL: &str = "original";
+
+/// 各类翻译条目的公共部分
+pub(crate) trait TranslationEntry {
+    fn country(&self) -> Option<&str>;
+    fn to_data(&self) -> TranslationData;
+
+    fn has_content(&self) -> bool {
+        !is_empty_translation(&self.to_data())
     }
-    
-    let preferred = translations.iter()
-        .find(|t| t.country.as_deref() == Some("cn"))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("tw")))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("hk")))
-        .or_else(|| translations.first());
-    
-    preferred.map(|t| TranslationData {
-        title: t.title.clone(),
-        overview: t.overview.clone(),
-        tagline: t.tagline.clone(),
-        updated_at: cache::get_timestamp() as u64,
-    })
 }
 
-fn process_show_translations(translations: ShowTranslations) -> Option<TranslationData> {
-    if translations.is_empty() {
-        return None;
-    }
-    
-    let preferred = translations.iter()
-        .find(|t| t.country.as_deref() == Some("cn"))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("tw")))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("hk")))
-        .or_else(|| translations.first());
-    
-    preferred.map(|t| TranslationData {
-        title: t.title.clone(),
-        overview: t.overview.clone(),
-        tagline: t.tagline.clone(),
-        updated_at: cache::get_timestamp() as u64,
-    })
+// 标题和简介都为空的翻译不可用，缓存中的空记录也以此判断
+fn is_empty_translation(data: &TranslationData) -> bool {
+    data.title.as_ref().is_none_or(|t| t.is_empty()) && data.overview.as_ref().is_none_or(|o| o.is_empty())
 }
 
-fn process_season_translations(translations: SeasonTranslations) -> Option<TranslationData> {
-    if translations.is_empty() {
-        return None;
-    }
-    
-    let preferred = translations.iter()
-        .find(|t| t.country.as_deref() == Some("cn"))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("tw")))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("hk")))
-        .or_else(|| translations.first());
-    
-    preferred.map(|t| TranslationData {
-        title: t.title.clone(),
-        overview: t.overview.clone(),
-        tagline: None,
-        updated_at: cache::get_timestamp() as u64,
-    })
+macro_rules! impl_translation_entry {
+    ($ty:ty, $tagline:expr) => {
+        impl TranslationEntry for $ty {
+            fn country(&self) -> Option<&str> {
+                self.country.as_deref()
+            }
+
+            fn to_data(&self) -> TranslationData {
+                TranslationData {
+                    title: self.title.clone(),
+                    overview: self.overview.clone(),
+                    tagline: $tagline(self),
+                    updated_at: cache::get_timestamp() as u64,
+                }
+            }
+        }
+    };
 }
 
+impl_translation_entry!(MovieTranslation, |t: &MovieTranslation| t.tagline.clone());
+impl_translation_entry!(ShowTranslation, |t: &ShowTranslation| t.tagline.clone());
+impl_translation_entry!(SeasonTranslation, |_: &SeasonTranslation| None);
+impl_translation_entry!(EpisodeTranslation, |_: &EpisodeTranslation| None);
 
-fn process_episode_translations(translations: EpisodeTranslations) -> Option<TranslationData> {
-    if translations.is_empty() {
+// zh-TW -> ("zh", Some("tw"))，Trakt 的地区码为小写；original 返回 None
+fn split_tag(tag: &str) -> Option<(String, Option<String>)> {
+    if tag == ORIGINAL {
         return None;
     }
-    
-    let preferred = translations.iter()
-        .find(|t| t.country.as_deref() == Some("cn"))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("tw")))
-        .or_else(|| translations.iter().find(|t| t.country.as_deref() == Some("hk")))
-        .or_else(|| translations.first());
-    
-    preferred.map(|t| TranslationData {
-        title: t.title.clone(),
-        overview: t.overview.clone(),
-        tagline: None,
-        updated_at: cache::get_timestamp() as u64,
-    })
+    let mut parts = tag.splitn(2, '-');
+    let language = parts.next().unwrap_or_default().to_lowercase();
+    let country = parts.next().map(|c| c.to_lowercase());
+    Some((language, country))
+}
+
+// 在同一语言的翻译中选出匹配地区的一条，没有指定地区时取第一条；标题和简介都为空的不算
+fn process_translations<T: TranslationEntry>(translations: &[T], country: Option<&str>) -> Option<TranslationData> {
+    translations
+        .iter()
+        .filter(|t| t.has_content())
+        .find(|t| match country {
+            Some(country) => t.country().is_some_and(|c| c.eq_ignore_ascii_case(country)),
+            None => true,
+        })
+        .map(|t| t.to_data())
 }
@@ -0,0 +1,117 @@
+use crate::db::{cache, DbPool};
+use crate::trakt_api::{api_client, Entry, API};
+use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+use log::{error, info};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+// 上次同步 updates 的时间戳 (ms)，保存在 app_config
+const UPDATES_SYNCED_KEY: &str = "media_updates_synced_at";
+const UPDATES_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// Trakt 只支持查询 30 天内的更新，留一天余量
+const UPDATES_MAX_DAYS: i64 = 29;
+const UPDATES_PAGE_LIMIT: u32 = 100;
+const UPDATES_MAX_PAGES: u32 = 100;
+
+/// 启动后台任务：定期查询 Trakt 上有变化的电影/剧集，只把变化了的缓存条目标记为陈旧
+pub fn spawn_media_updates_job(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = sync_media_updates(&app).await {
+                error!("同步媒体更新失败: {}", e);
+            }
+            tokio::time::sleep(UPDATES_INTERVAL).await;
+        }
+    });
+}
+
+async fn sync_media_updates(app: &AppHandle) -> Result<(), u16> {
+    let pool = match app.try_state::<DbPool>() {
+        Some(pool) => pool.0.clone(),
+        None => return Ok(()),
+    };
+
+    let now = cache::get_timestamp();
+    let last_synced = cache::get_config(&pool, UPDATES_SYNCED_KEY)
+        .await
+        .and_then(|v| v.as_i64());
+
+    let start = last_synced.and_then(|ts| Utc.timestamp_millis_opt(ts).single());
+    let start = match start {
+        Some(start) if Utc::now() - start < ChronoDuration::days(UPDATES_MAX_DAYS) => start,
+        // 从未同步过或间隔太久，无法得知哪些条目有变化，全部标记为陈旧
+        _ => {
+            let mut marked = 0;
+            for media_type in cache::UPDATE_TRACKED_MOVIE_TYPES.iter().chain(cache::UPDATE_TRACKED_SHOW_TYPES.iter()) {
+                marked += cache::mark_media_type_stale(&pool, media_type).await;
+            }
+            info!("No recent media updates sync, marked {} cached entries stale", marked);
+            let _ = cache::set_config(&pool, UPDATES_SYNCED_KEY, &json!(now)).await;
+            return Ok(());
+        }
+    };
+    // 按日期查询会包含当天较早的更新，重复标记不影响结果
+    let start_date = start.format("%Y-%m-%d").to_string();
+
+    let movie_marked = mark_updated(app, &pool, &API.movie.updates, &start_date, &cache::UPDATE_TRACKED_MOVIE_TYPES).await?;
+    let show_marked = mark_updated(app, &pool, &API.shows.updates, &start_date, &cache::UPDATE_TRACKED_SHOW_TYPES).await?;
+
+    let _ = cache::set_config(&pool, UPDATES_SYNCED_KEY, &json!(now)).await;
+    info!(
+        "Media updates since {} synced, {} movie and {} show cache entries marked stale",
+        start_date, movie_marked, show_marked
+    );
+    Ok(())
+}
+
+async fn mark_updated(
+    app: &AppHandle,
+    pool: &sqlx::SqlitePool,
+    entry: &Entry,
+    start_date: &str,
+    media_types: &[&str],
+) -> Result<u64, u16> {
+    // 复制一份 client，翻页期间不占用全局锁
+    let mut client = api_client(app).await;
+    let uri = entry.uri.replace("start_date", start_date);
+
+    let mut ids: Vec<u32> = Vec::new();
+    let mut complete = false;
+    for page in 1..=UPDATES_MAX_PAGES {
+        let result = client
+            .req_api(
+                app,
+                entry.method.as_str(),
+                uri.clone(),
+                None,
+                None,
+                Some(UPDATES_PAGE_LIMIT),
+                Some(page),
+                false,
+            )
+            .await?;
+
+        let page_ids: Vec<u32> = match result {
+            Value::Array(items) => items.iter().filter_map(|v| v.as_u64()).map(|id| id as u32).collect(),
+            _ => Vec::new(),
+        };
+        let count = page_ids.len() as u32;
+        ids.extend(page_ids);
+        if count < UPDATES_PAGE_LIMIT {
+            complete = true;
+            break;
+        }
+    }
+
+    let mut marked = 0;
+    for media_type in media_types {
+        marked += if complete {
+            cache::mark_media_cache_stale(pool, media_type, &ids).await
+        } else {
+            // 更新太多没有取完，保守起见整体标记
+            cache::mark_media_type_stale(pool, media_type).await
+        };
+    }
+    Ok(marked)
+}
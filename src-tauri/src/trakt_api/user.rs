@@ -2,14 +2,15 @@ use crate::model::movie::Movie;
 use crate::model::shows::{Episode, Season, Show};
 use crate::model::user::Stats;
 use crate::model::user::UserProfile;
-use crate::trakt_api::{ApiClient, API};
+use crate::trakt_api::{api_client, API};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tauri::command;
-use tauri::{AppHandle, Manager, Emitter};
-use tokio::sync::Mutex;
-use crate::db::{DbPool, cache};
-use log::{info, error};
+use tauri::AppHandle;
+use crate::db::policy;
+use crate::db::cache::CacheKey;
+use crate::trakt_api::cached_fetch::cached_fetch;
+use log::error;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Watched {
@@ -54,47 +55,17 @@ pub struct HistoryItem {
 
 #[command]
 pub async fn get_user_profile(app: AppHandle) -> Result<UserProfile, u16> {
-    let cache_key = "user_profile_me"; // 简化，默认获取当前登录用户
+    // 简化，默认获取当前登录用户
     // 如果需要支持查看他人 profile，需传入 username 并作为 key 的一部分
-    
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_user_data_cache(&pool.0, cache_key).await {
-            if let Ok(profile) = serde_json::from_value::<UserProfile>(result.data) {
-                cache_data = Some(profile);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
-
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            return Ok(data);
-        }
-        
-        let app_clone = app.clone();
-        tokio::spawn(async move {
-            match fetch_and_cache_profile(&app_clone, cache_key).await {
-                Ok(new_data) => {
-                    let _ = app_clone.emit("user-data-update", serde_json::json!({
-                        "key": cache_key,
-                        "data": new_data
-                    }));
-                },
-                Err(_) => {}
-            }
-        });
-        return Ok(data);
-    }
-
-    fetch_and_cache_profile(&app, cache_key).await
+    let cache_key = CacheKey::Key("user_profile_me".to_string());
+    cached_fetch(&app, &policy::USER_PROFILE, cache_key, |app| async move {
+        fetch_profile(&app).await
+    })
+    .await
 }
 
-async fn fetch_and_cache_profile(app: &AppHandle, cache_key: &str) -> Result<UserProfile, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+async fn fetch_profile(app: &AppHandle) -> Result<UserProfile, u16> {
+    let mut client = api_client(app).await;
     let result = client
         .req_api(
             app,
@@ -106,17 +77,11 @@ async fn fetch_and_cache_profile(app: &AppHandle, cache_key: &str) -> Result<Use
             None,
             true,
         )
-        .await;
-    match result {
-        Ok(result) => {
-            let user_profile = serde_json::from_value::<UserProfile>(result.clone()).unwrap();
-            if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_user_data_cache(&pool.0, cache_key, &result).await;
-            }
-            Ok(user_profile)
-        }
-        Err(e) => Err(e)
-    }
+        .await?;
+    serde_json::from_value::<UserProfile>(result).map_err(|e| {
+        error!("解析用户资料失败: {:?}", e);
+        500
+    })
 }
 
 #[command]
@@ -127,46 +92,19 @@ pub async fn get_watched(
     no_season: bool,
 ) -> Result<Vec<Watched>, u16> {
     // Watched 列表通常用于进度计算，数据量可能较大
-    // 同样适用 SWR
     let cache_key = format!("watched_{}_{}", id, select_type.clone().unwrap_or("all".to_string()));
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
-            if let Ok(watched) = serde_json::from_value::<Vec<Watched>>(result.data) {
-                cache_data = Some(watched);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
-
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            return Ok(data);
-        }
-        
-        let app_clone = app.clone();
-        let id_clone = id.clone();
-        let type_clone = select_type.clone();
-        
-        tokio::spawn(async move {
-            let _ = fetch_and_cache_watched(&app_clone, &id_clone, type_clone, &cache_key).await;
-        });
-        return Ok(data);
-    }
-
-    fetch_and_cache_watched(&app, &id, select_type, &cache_key).await
+    cached_fetch(&app, &policy::USER_WATCHED, CacheKey::Key(cache_key), move |app| async move {
+        fetch_watched(&app, &id, select_type).await
+    })
+    .await
 }
 
-async fn fetch_and_cache_watched(
+async fn fetch_watched(
     app: &AppHandle, 
     id: &str, 
     select_type: Option<String>,
-    cache_key: &str
 ) -> Result<Vec<Watched>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
+    let mut client = api_client(app).await;
     let mut uri = API.user.watched.uri.clone();
     uri = uri.replace("id", id);
     if let Some(t) = select_type {
@@ -183,63 +121,25 @@ async fn fetch_and_cache_watched(
             None,
             true,
         )
-        .await;
-    match result {
-        Ok(result) => {
-            let watched = serde_json::from_value::<Vec<Watched>>(result.clone()).unwrap();
-            if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_user_data_cache(&pool.0, cache_key, &result).await;
-            }
-            Ok(watched)
-        }
-        Err(e) => Err(e)
-    }
+        .await?;
+    serde_json::from_value::<Vec<Watched>>(result).map_err(|e| {
+        error!("解析观看列表失败: {:?}", e);
+        500
+    })
 }
 
 #[command]
 pub async fn get_user_stats(app: AppHandle, id: String) -> Result<Stats, u16> {
     let cache_key = format!("stats_{}", id);
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
-            if let Ok(stats) = serde_json::from_value::<Stats>(result.data) {
-                cache_data = Some(stats);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
-
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            return Ok(data);
-        }
-        
-        let app_clone = app.clone();
-        let id_clone = id.clone();
-        tokio::spawn(async move {
-            match fetch_and_cache_stats(&app_clone, &id_clone, &cache_key).await {
-                Ok(new_data) => {
-                    let _ = app_clone.emit("user-data-update", serde_json::json!({
-                        "key": cache_key,
-                        "data": new_data
-                    }));
-                }
-                Err(_) => {}
-            }
-        });
-        return Ok(data);
-    }
-
-    fetch_and_cache_stats(&app, &id, &cache_key).await
+    cached_fetch(&app, &policy::USER_STATS, CacheKey::Key(cache_key), move |app| async move {
+        fetch_stats(&app, &id).await
+    })
+    .await
 }
 
-async fn fetch_and_cache_stats(app: &AppHandle, id: &str, cache_key: &str) -> Result<Stats, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
-    let mut uri = API.user.stats.uri.clone();
-    uri = uri.replace("id", id);
+async fn fetch_stats(app: &AppHandle, id: &str) -> Result<Stats, u16> {
+    let mut client = api_client(app).await;
+    let uri = API.user.stats.uri.replace("id", id);
     let result = client
         .req_api(
             app,
@@ -251,17 +151,11 @@ async fn fetch_and_cache_stats(app: &AppHandle, id: &str, cache_key: &str) -> Re
             None,
             true,
         )
-        .await;
-    match result {
-        Ok(result) => {
-            let user_stats = serde_json::from_value::<Stats>(result.clone()).unwrap();
-            if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_user_data_cache(&pool.0, cache_key, &result).await;
-            }
-            Ok(user_stats)
-        }
-        Err(e) => Err(e)
-    }
+        .await?;
+    serde_json::from_value::<Stats>(result).map_err(|e| {
+        error!("解析用户统计失败: {:?}", e);
+        500
+    })
 }
 
 #[command]
@@ -271,56 +165,19 @@ pub async fn get_collection(
     select_type: String,
 ) -> Result<Vec<CollectionItem>, u16> {
     let cache_key = format!("collection_{}_{}", select_type, id);
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
-            if let Ok(collection) = serde_json::from_value::<Vec<CollectionItem>>(result.data) {
-                cache_data = Some(collection);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
-
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            return Ok(data);
-        }
-        
-        let app_clone = app.clone();
-        let id_clone = id.clone();
-        let type_clone = select_type.clone();
-        
-        tokio::spawn(async move {
-            match fetch_and_cache_collection(&app_clone, &id_clone, &type_clone, &cache_key).await {
-                Ok(new_data) => {
-                    info!("Background update success for collection {}/{}", id_clone, type_clone);
-                    let _ = app_clone.emit("user-data-update", serde_json::json!({
-                        "key": cache_key,
-                        "data": new_data
-                    }));
-                },
-                Err(e) => error!("Background update failed for collection: {}", e),
-            }
-        });
-        
-        return Ok(data);
-    }
-
-    fetch_and_cache_collection(&app, &id, &select_type, &cache_key).await
+    cached_fetch(&app, &policy::USER_COLLECTION, CacheKey::Key(cache_key), move |app| async move {
+        fetch_collection(&app, &id, &select_type).await
+    })
+    .await
 }
 
-async fn fetch_and_cache_collection(
+async fn fetch_collection(
     app: &AppHandle, 
     id: &str, 
     select_type: &str,
-    cache_key: &str
 ) -> Result<Vec<CollectionItem>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
-    let mut uri = API.user.collection.uri.clone();
-    uri = uri.replace("id", id).replace("type", select_type);
+    let mut client = api_client(app).await;
+    let uri = API.user.collection.uri.replace("id", id).replace("type", select_type);
     
     let result = client
         .req_api(
@@ -333,20 +190,11 @@ async fn fetch_and_cache_collection(
             None,
             true,
         )
-        .await;
-        
-    match result {
-        Ok(result) => {
-            let collection = serde_json::from_value::<Vec<CollectionItem>>(result.clone()).unwrap();
-            
-            if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_user_data_cache(&pool.0, cache_key, &result).await;
-            }
-            
-            Ok(collection)
-        }
-        Err(e) => Err(e)
-    }
+        .await?;
+    serde_json::from_value::<Vec<CollectionItem>>(result).map_err(|e| {
+        error!("解析收藏列表失败: {:?}", e);
+        500
+    })
 }
 
 #[command]
@@ -356,56 +204,19 @@ pub async fn get_watchlist(
     select_type: String,
 ) -> Result<Vec<WatchlistItem>, u16> {
     let cache_key = format!("watchlist_{}_{}", select_type, id);
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    if let Some(pool) = app.try_state::<DbPool>() {
-        if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
-            if let Ok(watchlist) = serde_json::from_value::<Vec<WatchlistItem>>(result.data) {
-                cache_data = Some(watchlist);
-                should_fetch = result.is_stale;
-            }
-        }
-    }
-
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            return Ok(data);
-        }
-        
-        let app_clone = app.clone();
-        let id_clone = id.clone();
-        let type_clone = select_type.clone();
-        
-        tokio::spawn(async move {
-            match fetch_and_cache_watchlist(&app_clone, &id_clone, &type_clone, &cache_key).await {
-                Ok(new_data) => {
-                    info!("Background update success for watchlist {}/{}", id_clone, type_clone);
-                    let _ = app_clone.emit("user-data-update", serde_json::json!({
-                        "key": cache_key,
-                        "data": new_data
-                    }));
-                },
-                Err(e) => error!("Background update failed for watchlist: {}", e),
-            }
-        });
-        
-        return Ok(data);
-    }
-
-    fetch_and_cache_watchlist(&app, &id, &select_type, &cache_key).await
+    cached_fetch(&app, &policy::USER_WATCHLIST, CacheKey::Key(cache_key), move |app| async move {
+        fetch_watchlist(&app, &id, &select_type).await
+    })
+    .await
 }
 
-async fn fetch_and_cache_watchlist(
+async fn fetch_watchlist(
     app: &AppHandle, 
     id: &str, 
     select_type: &str,
-    cache_key: &str
 ) -> Result<Vec<WatchlistItem>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
-    let mut uri = API.user.watchlist.uri.clone();
-    uri = uri.replace("id", id).replace("type", select_type);
+    let mut client = api_client(app).await;
+    let uri = API.user.watchlist.uri.replace("id", id).replace("type", select_type);
 
     let mut params = HashMap::new();
     params.insert("extended".to_string(), "full".to_string());
@@ -422,19 +233,81 @@ async fn fetch_and_cache_watchlist(
             None,
             true,
         )
-        .await;
+        .await?;
+    serde_json::from_value::<Vec<WatchlistItem>>(result).map_err(|e| {
+        error!("解析待看列表失败: {:?}", e);
+        500
+    })
+}
+
+/// 观看记录筛选条件，全部为空时等同于不筛选
+/// item_id 需要同时指定 select_type；start_at / end_at 可以是日期 (2024-03-01) 或 ISO 8601 时间
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct HistoryFilter {
+    pub select_type: Option<String>,
+    pub item_id: Option<u32>,
+    pub start_at: Option<String>,
+    pub end_at: Option<String>,
+}
 
-    match result {
-        Ok(result) => {
-            let watchlist = serde_json::from_value::<Vec<WatchlistItem>>(result.clone()).unwrap();
-            
-            if let Some(pool) = app.try_state::<DbPool>() {
-                cache::set_user_data_cache(&pool.0, cache_key, &result).await;
+const HISTORY_TYPES: [&str; 4] = ["movies", "shows", "seasons", "episodes"];
+
+impl HistoryFilter {
+    fn validate(&self) -> Result<(), u16> {
+        if let Some(t) = &self.select_type {
+            if !HISTORY_TYPES.contains(&t.as_str()) {
+                return Err(400);
             }
-            
-            Ok(watchlist)
+        } else if self.item_id.is_some() {
+            return Err(400);
+        }
+        Ok(())
+    }
+
+    /// 拼接到 /users/id/history 之后的路径部分
+    fn path(&self) -> String {
+        match (&self.select_type, self.item_id) {
+            (Some(t), Some(item_id)) => format!("/{}/{}", t, item_id),
+            (Some(t), None) => format!("/{}", t),
+            _ => String::new(),
+        }
+    }
+
+    fn to_params(&self) -> Result<HashMap<String, String>, u16> {
+        let mut params = HashMap::new();
+        if let Some(start) = &self.start_at {
+            params.insert("start_at".to_string(), normalize_history_time(start, false)?);
+        }
+        if let Some(end) = &self.end_at {
+            params.insert("end_at".to_string(), normalize_history_time(end, true)?);
+        }
+        Ok(params)
+    }
+
+    // 用规范化后的时间，同一时间的不同写法共用一份缓存
+    fn cache_key(&self) -> Result<String, u16> {
+        let params = self.to_params()?;
+        Ok(format!(
+            "_{}_{}_{}_{}",
+            self.select_type.as_deref().unwrap_or("all"),
+            self.item_id.map(|id| id.to_string()).unwrap_or_default(),
+            params.get("start_at").map(String::as_str).unwrap_or(""),
+            params.get("end_at").map(String::as_str).unwrap_or(""),
+        ))
+    }
+}
+
+// 只有日期时，开始取当天 0 点，结束取当天最后一刻，这样 end_at=2024-03-31 包含 31 号全天
+fn normalize_history_time(value: &str, is_end: bool) -> Result<String, u16> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&chrono::Utc).format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+    }
+    match chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => {
+            let time = if is_end { "23:59:59.999" } else { "00:00:00.000" };
+            Ok(format!("{}T{}Z", date.format("%Y-%m-%d"), time))
         }
-        Err(e) => Err(e)
+        Err(_) => Err(400),
     }
 }
 
@@ -444,102 +317,44 @@ pub async fn get_history(
     id: String,
     page: Option<u32>,
     limit: Option<u32>,
+    filter: Option<HistoryFilter>,
 ) -> Result<Vec<HistoryItem>, u16> {
     let current_page = page.unwrap_or(1);
     let current_limit = limit.unwrap_or(10);
-    let cache_key = format!("history_{}_p{}_l{}", id, current_page, current_limit);
-    
-    let mut cache_data = None;
-    let mut should_fetch = true;
-
-    // 仅缓存第一页
-    let is_first_page = current_page == 1;
-    
-    if is_first_page {
-        if let Some(pool) = app.try_state::<DbPool>() {
-            if let Some(result) = cache::get_user_data_cache(&pool.0, &cache_key).await {
-                if let Ok(history) = serde_json::from_value::<Vec<HistoryItem>>(result.data) {
-                    cache_data = Some(history);
-                    should_fetch = result.is_stale;
-                }
-            }
-        }
-    }
-
-    if let Some(data) = cache_data {
-        if !should_fetch {
-            return Ok(data);
-        }
-        
-        // SWR
-        let app_clone = app.clone();
-        let id_clone = id.clone();
-        tokio::spawn(async move {
-            match fetch_and_cache_history(&app_clone, &id_clone, page, limit, &cache_key, is_first_page).await {
-                Ok(new_data) => {
-                    let _ = app_clone.emit("user-data-update", serde_json::json!({
-                        "key": cache_key,
-                        "data": new_data
-                    }));
-                },
-                Err(_) => {}
-            }
-        });
-        return Ok(data);
-    }
-
-    fetch_and_cache_history(&app, &id, page, limit, &cache_key, is_first_page).await
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+    let cache_key = format!("history_{}_p{}_l{}{}", id, current_page, current_limit, filter.cache_key()?);
+    cached_fetch(&app, &policy::USER_HISTORY, CacheKey::Key(cache_key), move |app| async move {
+        fetch_history(&app, &id, current_page, current_limit, &filter).await
+    })
+    .await
 }
 
-async fn fetch_and_cache_history(
+async fn fetch_history(
     app: &AppHandle, 
     id: &str,
-    page: Option<u32>,
-    limit: Option<u32>,
-    cache_key: &str,
-    should_cache: bool
+    page: u32,
+    limit: u32,
+    filter: &HistoryFilter,
 ) -> Result<Vec<HistoryItem>, u16> {
-    let client = app.state::<Mutex<ApiClient>>();
-    let mut client = client.lock().await;
-    let mut uri = API.user.history.uri.clone();
-    uri = uri.replace("id", id);
-
-    let mut query_parts: Vec<String> = Vec::new();
-    if let Some(p) = page {
-        query_parts.push(format!("page={}", p));
-    }
-    if let Some(l) = limit {
-        query_parts.push(format!("limit={}", l));
-    }
-    if !query_parts.is_empty() {
-        uri = format!("{}?{}", uri, query_parts.join("&"));
-    }
+    let mut client = api_client(app).await;
+    let uri = format!("{}{}", API.user.history.uri.replace("id", id), filter.path());
 
     let result = client
         .req_api(
             app,
             API.user.history.method.as_str(),
             uri,
+            Some(filter.to_params()?),
             None,
-            None,
-            None,
-            None,
+            Some(limit),
+            Some(page),
             true,
         )
-        .await;
-        
-    match result {
-        Ok(result) => {
-            let history = serde_json::from_value::<Vec<HistoryItem>>(result.clone()).unwrap();
-            
-            if should_cache {
-                if let Some(pool) = app.try_state::<DbPool>() {
-                    cache::set_user_data_cache(&pool.0, cache_key, &result).await;
-                }
-            }
-            
-            Ok(history)
-        }
-        Err(e) => Err(e)
-    }
+        .await?;
+
+    serde_json::from_value::<Vec<HistoryItem>>(result).map_err(|e| {
+        error!("解析观看记录失败: {:?}", e);
+        500
+    })
 }
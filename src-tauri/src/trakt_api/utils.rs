@@ -1,22 +1,24 @@
-use base64::{engine::general_purpose, Engine as _};
-use tauri::command;
+use crate::image_cache::prefetch::{self, PrefetchJob};
+use crate::image_cache::{self, ImageVariant};
+use tauri::{command, AppHandle};
 
+/// 图片先缓存到本地，返回 mytv-img 协议地址供 webview 直接加载
+/// variant 为 poster_small / poster_medium / fanart_medium / fanart_blur / thumb 时返回缩放后的图片，默认原图
 #[command]
-pub async fn get_proxied_image(url: String) -> Result<String, String> {
-    let client = reqwest::Client::builder()
-        .user_agent("MyTV/1.0")
-        .build()
-        .map_err(|e| e.to_string())?;
+pub async fn get_proxied_image(app: AppHandle, url: String, variant: Option<ImageVariant>) -> Result<String, String> {
+    let key = image_cache::get_or_render(&app, &url, variant.unwrap_or(ImageVariant::Original)).await?;
+    Ok(image_cache::scheme_url(&key))
+}
 
-    let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
-    let content_type = resp
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("image/jpeg")
-        .to_string();
+/// 把图片加入后台预取队列，group 用于离开页面时取消
+#[command]
+pub async fn prefetch_images(app: AppHandle, group: String, items: Vec<PrefetchJob>) -> Result<(), String> {
+    prefetch::enqueue(&app, &group, items);
+    Ok(())
+}
 
-    let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
-    let base64_data = general_purpose::STANDARD.encode(&bytes);
-    Ok(format!("data:{};base64,{}", content_type, base64_data))
+/// 取消某个列表还在排队的预取，返回移除的数量
+#[command]
+pub async fn cancel_image_prefetch(group: String) -> Result<usize, String> {
+    Ok(prefetch::cancel(&group))
 }